@@ -0,0 +1,86 @@
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::lockfile::lock_path;
+use crate::{sys, Error, LockType};
+
+/// Rewrites the file at `path` under an exclusive lock, without ever leaving it torn: `func`
+/// writes the new contents to a temporary file in the same directory, which is synced and
+/// renamed over the original before the lock is released. Errors from `func` leave the file as
+/// it was.
+///
+/// Waiting lockers notice the file was replaced and lock the new one, so this only works if
+/// everyone locks the file through this crate's path-based helpers (this,
+/// [`update`](crate::update) or `FileMutex`) rather than an `FdLock` on a handle opened earlier.
+pub fn atomic_write<P, T, E, Func>(path: P, func: Func) -> Result<T, E>
+where
+    P: AsRef<Path>,
+    E: From<Error>,
+    Func: FnOnce(&mut File) -> Result<T, E>,
+{
+    let path = path.as_ref();
+    let lock = lock_path(path, LockType::Exclusive, true)?;
+    let res = replace(path, func)?;
+    lock.unlock().map_err(Error::from)?;
+    Ok(res)
+}
+
+/// Replaces the file at `path` with what `write` puts in a new file next to it. The caller
+/// holds the lock on `path` (see `lock_path`), so waiters take the new file once it is released.
+pub(crate) fn replace<T, E: From<Error>, Func: FnOnce(&mut File) -> Result<T, E>>(
+    path: &Path,
+    write: Func,
+) -> Result<T, E> {
+    let tmp = tmp_path(path);
+    let mut f = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&tmp)
+        .map_err(sys::io_error)?;
+    if let Ok(meta) = std::fs::metadata(path) {
+        let _ = f.set_permissions(meta.permissions());
+    }
+    let res = match write(&mut f) {
+        Ok(res) => res,
+        Err(e) => {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(e);
+        }
+    };
+    if let Err(e) = f.sync_all().and_then(|()| std::fs::rename(&tmp, path)) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(sys::io_error(e).into());
+    }
+    sync_dir(path);
+    Ok(res)
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        COUNT.fetch_add(1, Ordering::Relaxed)
+    ));
+    path.with_file_name(name)
+}
+
+// Makes the rename itself durable. Best effort: not every filesystem allows it.
+#[cfg(unix)]
+fn sync_dir(path: &Path) {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) {}
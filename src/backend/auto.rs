@@ -0,0 +1,115 @@
+use std::path::Path;
+
+use super::{DotLock, LockBackend, SmbCompat};
+use crate::sys::{self, AsFile};
+use crate::{Error, LockStyle, LockType};
+
+/// The kind of filesystem a path lives on, as far as locking is concerned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filesystem {
+    /// NFS, where `flock(2)` is either local to the client (so other clients never see the
+    /// lock) or emulated with record locks, depending on the kernel and mount options.
+    Nfs,
+    /// SMB/CIFS.
+    Smb,
+    /// Anything else, including platforms where the probe isn't implemented.
+    Other,
+}
+impl Filesystem {
+    /// Probes the filesystem holding `path` (with `statfs(2)`).
+    pub fn of<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        sys::filesystem(path.as_ref())
+    }
+}
+
+/// A backend picked to suit the filesystem holding the lock file. The chosen strategy is the
+/// variant, so it can be inspected through [`FdLock::backend`](crate::FdLock::backend).
+///
+/// `Send` and `Sync`, as each strategy is.
+#[derive(Clone, Debug)]
+pub enum Auto {
+    Style(LockStyle),
+    DotLock(DotLock),
+    SmbCompat(SmbCompat),
+}
+impl Auto {
+    /// Probes `path` (or its parent directory, if it doesn't exist yet) and picks a backend
+    /// for it: `fcntl(2)` record locks (which NFS forwards to the server's lock manager) on
+    /// NFS, [`SmbCompat`] on SMB/CIFS, and the default [`LockStyle`] otherwise.
+    pub fn detect<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        Ok(match probe(path)? {
+            Filesystem::Nfs => Auto::Style(LockStyle::Fcntl),
+            Filesystem::Smb => Auto::SmbCompat(SmbCompat::new(path)),
+            Filesystem::Other => Auto::Style(LockStyle::default()),
+        })
+    }
+    /// Like [`Auto::detect`], but falls back to a [`DotLock`] at `<path>.lock` on NFS, for
+    /// mounts without a working lock manager (such as NFSv3 with `nolock`).
+    pub fn detect_with_dotlock<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        Ok(match probe(path)? {
+            Filesystem::Nfs => {
+                let mut lock_path = path.as_os_str().to_owned();
+                lock_path.push(".lock");
+                Auto::DotLock(DotLock::new(lock_path))
+            }
+            Filesystem::Smb => Auto::SmbCompat(SmbCompat::new(path)),
+            Filesystem::Other => Auto::Style(LockStyle::default()),
+        })
+    }
+}
+// The lock file usually doesn't exist yet, so fall back to the directory that will hold it.
+fn probe(path: &Path) -> Result<Filesystem, Error> {
+    match path.parent() {
+        Some(parent) if !path.exists() => Filesystem::of(if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        }),
+        _ => Filesystem::of(path),
+    }
+}
+impl LockBackend for Auto {
+    fn acquire<F: AsFile>(&self, f: &F, lock_type: &LockType) -> Result<(), Error> {
+        match self {
+            Auto::Style(style) => style.acquire(f, lock_type),
+            Auto::DotLock(dotlock) => dotlock.acquire(f, lock_type),
+            Auto::SmbCompat(smb) => smb.acquire(f, lock_type),
+        }
+    }
+    fn try_acquire<F: AsFile>(&self, f: &F, lock_type: &LockType) -> Result<(), Error> {
+        match self {
+            Auto::Style(style) => style.try_acquire(f, lock_type),
+            Auto::DotLock(dotlock) => dotlock.try_acquire(f, lock_type),
+            Auto::SmbCompat(smb) => smb.try_acquire(f, lock_type),
+        }
+    }
+    fn release<F: AsFile>(&self, f: &F) -> Result<(), Error> {
+        match self {
+            Auto::Style(style) => style.release(f),
+            Auto::DotLock(dotlock) => dotlock.release(f),
+            Auto::SmbCompat(smb) => smb.release(f),
+        }
+    }
+    fn convert<F: AsFile>(
+        &self,
+        f: &F,
+        from: &LockType,
+        to: &LockType,
+        blocking: bool,
+    ) -> Result<(), Error> {
+        match self {
+            Auto::Style(style) => style.convert(f, from, to, blocking),
+            Auto::DotLock(dotlock) => dotlock.convert(f, from, to, blocking),
+            Auto::SmbCompat(smb) => smb.convert(f, from, to, blocking),
+        }
+    }
+    fn is_held<F: AsFile>(&self, f: &F) -> Result<bool, Error> {
+        match self {
+            Auto::Style(style) => style.is_held(f),
+            Auto::DotLock(dotlock) => dotlock.is_held(f),
+            Auto::SmbCompat(smb) => smb.is_held(f),
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind as IOErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+use super::LockBackend;
+use crate::sys::{self, AsFile};
+use crate::{Error, LockType};
+
+/// The classic `.lock` file protocol (as in liblockfile): a uniquely named temporary file is
+/// hard linked to the lock path, which only succeeds for one holder at a time. Unlike kernel
+/// locks this works on any filesystem, including network mounts where `flock(2)` is
+/// unreliable, but the lock is not released if the holder crashes; see
+/// [`DotLock::stale_after`].
+///
+/// The guarded handle is not involved in locking, so it can be any file. Dot locks are always
+/// exclusive: `LockType::Shared` fails with [`Error::Unsupported`].
+///
+/// Releasing only removes the lock file this `DotLock` linked: one that was broken as stale and
+/// replaced in the meantime, or that was already released, is left alone and the release fails
+/// with [`Error::InvalidOperation`]. A clone starts out holding nothing.
+///
+/// `Send` and `Sync`. The lock is a file on disk, so any thread can release it.
+#[derive(Debug)]
+pub struct DotLock {
+    path: PathBuf,
+    stale_after: Option<Duration>,
+    retry_interval: Duration,
+    // While this holds the lock, what it wrote to the lock file.
+    held: Mutex<Option<String>>,
+}
+impl Clone for DotLock {
+    fn clone(&self) -> Self {
+        DotLock {
+            path: self.path.clone(),
+            stale_after: self.stale_after,
+            retry_interval: self.retry_interval,
+            held: Mutex::new(None),
+        }
+    }
+}
+impl DotLock {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        DotLock {
+            path: path.into(),
+            stale_after: None,
+            retry_interval: Duration::from_millis(100),
+            held: Mutex::new(None),
+        }
+    }
+    /// Treats a lock file whose modification time is older than `age` as left behind by a
+    /// crashed holder, and removes it. Holders that run longer have to
+    /// [`refresh`](DotLock::refresh) it more often than that.
+    ///
+    /// A stale file is first renamed aside and checked again, so a waiter that judged it stale
+    /// just as another replaced it with a fresh lock (or its holder refreshed it) puts it back
+    /// instead of removing it. Should a third process take the path while it's aside, putting
+    /// it back fails and so does the acquisition, with the error from link(2): both that
+    /// process and the fresh lock's holder then believe they hold the lock.
+    pub fn stale_after(mut self, age: Duration) -> Self {
+        self.stale_after = Some(age);
+        self
+    }
+    /// How long a blocking acquisition sleeps between attempts. Defaults to 100ms.
+    pub fn retry_interval(mut self, interval: Duration) -> Self {
+        self.retry_interval = interval;
+        self
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// Sets the lock file's modification time to now, so it isn't taken for stale while held.
+    /// Fails with [`Error::InvalidOperation`] if the file there doesn't record this process as
+    /// the holder.
+    pub fn refresh(&self) -> Result<(), Error> {
+        let f = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&self.path)
+            .map_err(sys::io_error)?;
+        if !records_us(&f)? {
+            return Err(Error::InvalidOperation);
+        }
+        f.set_modified(SystemTime::now()).map_err(sys::io_error)
+    }
+
+    fn held(&self) -> MutexGuard<'_, Option<String>> {
+        self.held.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn temp_path(&self) -> PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.path.with_file_name(format!(
+            ".{}.{}.{}.{}",
+            name,
+            sys::hostname(),
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ))
+    }
+
+    fn try_link(&self) -> Result<bool, Error> {
+        let temp = self.temp_path();
+        let mut f = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)
+            .map_err(sys::io_error)?;
+        // Our PID, as other dot lock implementations expect, and then the temporary file's
+        // name, which no other lock file has, to know ours by.
+        let contents = format!(
+            "{}\n{}\n",
+            std::process::id(),
+            temp.file_name().unwrap_or_default().to_string_lossy()
+        );
+        let written = f.write_all(contents.as_bytes());
+        drop(f);
+        let linked = written.and_then(|_| fs::hard_link(&temp, &self.path));
+        // link(2) over NFS can report failure when it actually succeeded, so the link count of
+        // the temporary file is the source of truth where available.
+        let acquired = match sys::link_count(&temp) {
+            Some(count) => count == 2,
+            None => linked.is_ok(),
+        };
+        if acquired {
+            *self.held() = Some(contents);
+        }
+        let _ = fs::remove_file(&temp);
+        match linked {
+            Err(e) if !acquired && e.kind() != IOErrorKind::AlreadyExists => Err(sys::io_error(e)),
+            _ => Ok(acquired),
+        }
+    }
+
+    fn break_stale(&self) -> Result<bool, Error> {
+        let stale_after = match self.stale_after {
+            Some(stale_after) => stale_after,
+            None => return Ok(false),
+        };
+        let judged = match identify(&self.path)? {
+            Some(judged) => judged,
+            // Released in the meantime.
+            None => return Ok(true),
+        };
+        let age = SystemTime::now()
+            .duration_since(judged.1)
+            .unwrap_or_default();
+        if age < stale_after {
+            return Ok(false);
+        }
+        // Removing it by path could remove a fresh lock that replaced it in the meantime, so
+        // move whatever is there aside and check that it's still the file judged stale.
+        let aside = self.temp_path();
+        match fs::rename(&self.path, &aside) {
+            Err(e) if e.kind() == IOErrorKind::NotFound => return Ok(true),
+            Err(e) => return Err(sys::io_error(e)),
+            Ok(()) => (),
+        }
+        match identify(&aside) {
+            Ok(aside) if aside == Some(judged) => (),
+            // Someone's fresh lock.
+            Ok(_) => {
+                self.put_back(&aside)?;
+                return Ok(false);
+            }
+            Err(e) => {
+                self.put_back(&aside)?;
+                return Err(e);
+            }
+        }
+        match fs::remove_file(&aside) {
+            Err(e) if e.kind() != IOErrorKind::NotFound => Err(sys::io_error(e)),
+            _ => Ok(true),
+        }
+    }
+
+    // Moves a lock file that was renamed to `aside` back into place. If the path was taken in
+    // the meantime there are two holders now, which the error reports.
+    fn put_back(&self, aside: &Path) -> Result<(), Error> {
+        let res = fs::hard_link(aside, &self.path).map_err(sys::io_error);
+        let _ = fs::remove_file(aside);
+        res
+    }
+}
+
+// Whether the lock file records this process as its holder.
+fn records_us(f: &File) -> Result<bool, Error> {
+    let contents = read(f)?;
+    Ok(contents.lines().next().map(|pid| pid.trim().parse()) == Some(Ok(std::process::id())))
+}
+
+fn read(mut f: &File) -> Result<String, Error> {
+    let mut contents = String::new();
+    std::io::Read::read_to_string(&mut f, &mut contents).map_err(sys::io_error)?;
+    Ok(contents)
+}
+
+// A file and its modification time. The id is missing where the platform can't tell files apart.
+type Identity = (Option<(u64, u64)>, SystemTime);
+
+// The file at `path`, or `None` if there isn't one.
+fn identify(path: &Path) -> Result<Option<Identity>, Error> {
+    let f = match File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == IOErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(sys::io_error(e)),
+    };
+    let modified = f
+        .metadata()
+        .and_then(|m| m.modified())
+        .map_err(sys::io_error)?;
+    Ok(Some((sys::file_id(&f).ok(), modified)))
+}
+impl LockBackend for DotLock {
+    fn acquire<F: AsFile>(&self, f: &F, lock_type: &LockType) -> Result<(), Error> {
+        loop {
+            match self.try_acquire(f, lock_type) {
+                Err(Error::WouldBlock) => std::thread::sleep(self.retry_interval),
+                res => return res,
+            }
+        }
+    }
+    fn try_acquire<F: AsFile>(&self, _f: &F, lock_type: &LockType) -> Result<(), Error> {
+        if let LockType::Shared = lock_type {
+            return Err(Error::Unsupported);
+        }
+        if self.try_link()? || (self.break_stale()? && self.try_link()?) {
+            Ok(())
+        } else {
+            Err(Error::WouldBlock)
+        }
+    }
+    fn release<F: AsFile>(&self, _f: &F) -> Result<(), Error> {
+        let mut held = self.held();
+        let written = match &*held {
+            Some(written) => written.clone(),
+            None => return Err(Error::InvalidOperation),
+        };
+        // The file there may not be ours any more (ours was broken as stale and the path taken
+        // again), so as in `break_stale` it's moved aside before it's known to be ours.
+        let aside = self.temp_path();
+        match fs::rename(&self.path, &aside) {
+            Err(e) if e.kind() == IOErrorKind::NotFound => {
+                *held = None;
+                return Err(Error::InvalidOperation);
+            }
+            Err(e) => return Err(sys::io_error(e)),
+            Ok(()) => (),
+        }
+        let ours = File::open(&aside)
+            .map_err(sys::io_error)
+            .and_then(|f| read(&f))
+            .map(|contents| contents == written);
+        match ours {
+            Ok(true) => {
+                *held = None;
+                fs::remove_file(&aside).map_err(sys::io_error)
+            }
+            Ok(false) => {
+                *held = None;
+                self.put_back(&aside)?;
+                Err(Error::InvalidOperation)
+            }
+            Err(e) => {
+                self.put_back(&aside)?;
+                Err(e)
+            }
+        }
+    }
+}
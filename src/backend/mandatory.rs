@@ -0,0 +1,49 @@
+use super::LockBackend;
+use crate::sys::{self, AsFile};
+use crate::{Error, LockStyle, LockType};
+
+/// Whole-file record locks that the kernel enforces against *all* reads and writes, not just
+/// against other lockers. Before locking, the file and filesystem are checked for the required
+/// setup, failing with [`Error::Misconfigured`] if it's missing:
+///
+/// - on Linux, the filesystem must be mounted with `-o mand` (no longer supported since Linux
+///   5.15), and the file must have the setgid bit set and group execute cleared;
+/// - on Solaris and illumos, only the file mode is required;
+/// - on Windows, byte-range locks are always mandatory, so nothing is checked.
+///
+/// Other platforms fail with [`Error::Unsupported`].
+///
+/// `Send` and `Sync`: record locks belong to the process, not the thread that took them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Mandatory;
+impl Mandatory {
+    #[cfg(windows)]
+    const STYLE: LockStyle = LockStyle::Flock;
+    #[cfg(not(windows))]
+    const STYLE: LockStyle = LockStyle::Fcntl;
+}
+impl LockBackend for Mandatory {
+    fn acquire<F: AsFile>(&self, f: &F, lock_type: &LockType) -> Result<(), Error> {
+        sys::check_mandatory(f)?;
+        Self::STYLE.acquire(f, lock_type)
+    }
+    fn try_acquire<F: AsFile>(&self, f: &F, lock_type: &LockType) -> Result<(), Error> {
+        sys::check_mandatory(f)?;
+        Self::STYLE.try_acquire(f, lock_type)
+    }
+    fn release<F: AsFile>(&self, f: &F) -> Result<(), Error> {
+        Self::STYLE.release(f)
+    }
+    fn convert<F: AsFile>(
+        &self,
+        f: &F,
+        from: &LockType,
+        to: &LockType,
+        blocking: bool,
+    ) -> Result<(), Error> {
+        Self::STYLE.convert(f, from, to, blocking)
+    }
+    fn is_held<F: AsFile>(&self, f: &F) -> Result<bool, Error> {
+        Self::STYLE.is_held(f)
+    }
+}
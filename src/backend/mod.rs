@@ -0,0 +1,119 @@
+//! Pluggable locking strategies for [`FdLock`](crate::FdLock).
+
+use crate::sys::{self, AsFile};
+use crate::{Error, LockStyle, LockType};
+
+mod auto;
+mod dotlock;
+mod mandatory;
+mod registered;
+mod smb;
+
+pub use self::auto::{Auto, Filesystem};
+pub use self::dotlock::DotLock;
+pub use self::mandatory::Mandatory;
+pub use self::registered::{OnSelfConflict, Registered};
+pub use self::smb::SmbCompat;
+
+/// How an [`FdLock`](crate::FdLock) takes and releases its lock. The backend is stored in the
+/// guard, so it can carry whatever state releasing needs.
+///
+/// A guard is `Send` and `Sync` exactly when its handle and backend are. All the backends here
+/// are: none of their locks belong to a thread. A backend whose locks do has to be `!Send`
+/// (say, by holding a `PhantomData<*const ()>`), which keeps its guards on the locking thread.
+pub trait LockBackend {
+    /// Takes the lock, waiting for conflicting holders to release it.
+    fn acquire<F: AsFile>(&self, f: &F, lock_type: &LockType) -> Result<(), Error>;
+    /// Takes the lock, failing with [`Error::WouldBlock`] instead of waiting.
+    fn try_acquire<F: AsFile>(&self, f: &F, lock_type: &LockType) -> Result<(), Error>;
+    /// Releases a lock taken by [`acquire`](LockBackend::acquire) or
+    /// [`try_acquire`](LockBackend::try_acquire).
+    fn release<F: AsFile>(&self, f: &F) -> Result<(), Error>;
+    /// Changes a held lock from `from` to `to` without releasing it in between. Unsupported
+    /// unless overridden.
+    fn convert<F: AsFile>(
+        &self,
+        _f: &F,
+        _from: &LockType,
+        _to: &LockType,
+        _blocking: bool,
+    ) -> Result<(), Error> {
+        Err(Error::Unsupported)
+    }
+    /// Checks, without disturbing it, whether a lock this backend took on `f` is still in
+    /// place. Unsupported unless overridden.
+    fn is_held<F: AsFile>(&self, _f: &F) -> Result<bool, Error> {
+        Err(Error::Unsupported)
+    }
+}
+
+/// Picks the kernel primitive at runtime.
+impl LockBackend for LockStyle {
+    fn acquire<F: AsFile>(&self, f: &F, lock_type: &LockType) -> Result<(), Error> {
+        sys::lock(f, self, lock_type, true)
+    }
+    fn try_acquire<F: AsFile>(&self, f: &F, lock_type: &LockType) -> Result<(), Error> {
+        sys::lock(f, self, lock_type, false)
+    }
+    fn release<F: AsFile>(&self, f: &F) -> Result<(), Error> {
+        sys::unlock(f, self, true)
+    }
+    fn convert<F: AsFile>(
+        &self,
+        f: &F,
+        from: &LockType,
+        to: &LockType,
+        blocking: bool,
+    ) -> Result<(), Error> {
+        sys::relock(f, self, from, to, blocking)
+    }
+    fn is_held<F: AsFile>(&self, f: &F) -> Result<bool, Error> {
+        sys::is_held(f, self)
+    }
+}
+
+macro_rules! style_backend {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        ///
+        /// `Send` and `Sync`, like [`LockStyle`].
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+        pub struct $name;
+        impl LockBackend for $name {
+            fn acquire<F: AsFile>(&self, f: &F, lock_type: &LockType) -> Result<(), Error> {
+                LockStyle::$name.acquire(f, lock_type)
+            }
+            fn try_acquire<F: AsFile>(&self, f: &F, lock_type: &LockType) -> Result<(), Error> {
+                LockStyle::$name.try_acquire(f, lock_type)
+            }
+            fn release<F: AsFile>(&self, f: &F) -> Result<(), Error> {
+                LockStyle::$name.release(f)
+            }
+            fn convert<F: AsFile>(
+                &self,
+                f: &F,
+                from: &LockType,
+                to: &LockType,
+                blocking: bool,
+            ) -> Result<(), Error> {
+                LockStyle::$name.convert(f, from, to, blocking)
+            }
+            fn is_held<F: AsFile>(&self, f: &F) -> Result<bool, Error> {
+                LockStyle::$name.is_held(f)
+            }
+        }
+    };
+}
+
+style_backend!(
+    /// [`LockStyle::Flock`], fixed at compile time.
+    Flock
+);
+style_backend!(
+    /// [`LockStyle::Fcntl`], fixed at compile time.
+    Fcntl
+);
+style_backend!(
+    /// [`LockStyle::Ofd`], fixed at compile time.
+    Ofd
+);
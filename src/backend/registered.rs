@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::sync::{Condvar, Mutex, MutexGuard, OnceLock};
+
+use super::LockBackend;
+use crate::sys::{self, AsFile};
+use crate::{Error, LockStyle, LockType};
+
+/// What [`Registered`] does when the lock it is asked for conflicts with one this process
+/// holds through another handle.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum OnSelfConflict {
+    /// Fail with [`Error::SelfConflict`].
+    #[default]
+    Fail,
+    /// Wait for the other guard to be released, like a lock held by another process, or fail
+    /// with [`Error::WouldBlock`] if not waiting. If the thread waiting is also the one holding
+    /// the other guard, it waits forever.
+    Wait,
+}
+/// Another backend, plus a process-wide record of the locks taken through it, keyed by
+/// (device, inode). The kernel treats two handles of one file in the same process like two
+/// processes, so an exclusive lock through one silently blocks the other forever; this notices
+/// the conflict before asking the kernel.
+///
+/// Only locks taken through `Registered` backends are recorded. Locks through the same handle
+/// never conflict, as with the kernel.
+///
+/// `Send` and `Sync` when `B` is. The record is process-wide, so a release from another thread
+/// updates it just the same.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Registered<B: LockBackend = LockStyle> {
+    inner: B,
+    on_conflict: OnSelfConflict,
+}
+impl<B: LockBackend> Registered<B> {
+    pub fn new(inner: B, on_conflict: OnSelfConflict) -> Self {
+        Registered { inner, on_conflict }
+    }
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+    pub fn on_conflict(&self) -> OnSelfConflict {
+        self.on_conflict
+    }
+    // Waits out (or reports) conflicts, then records the lock before the kernel is asked, so
+    // another thread can't slip in between.
+    fn reserve<F: AsFile>(&self, f: &F, lock_type: &LockType, blocking: bool) -> Result<(), Error> {
+        let id = sys::file_id(f)?;
+        let fd = sys::as_raw(f) as usize;
+        let (held, released) = registry();
+        let mut held = lock(held);
+        loop {
+            let conflict = held.get(&id).is_some_and(|locks| {
+                locks.iter().any(|&(other, other_type)| {
+                    other != fd
+                        && (*lock_type == LockType::Exclusive || other_type == LockType::Exclusive)
+                })
+            });
+            if !conflict {
+                break;
+            }
+            match self.on_conflict {
+                OnSelfConflict::Fail => return Err(Error::SelfConflict),
+                OnSelfConflict::Wait if !blocking => return Err(Error::WouldBlock),
+                OnSelfConflict::Wait => {
+                    held = released.wait(held).unwrap_or_else(|e| e.into_inner())
+                }
+            }
+        }
+        record(&mut held, id, fd, lock_type);
+        Ok(())
+    }
+    fn take<F: AsFile, Func: FnOnce() -> Result<(), Error>>(
+        &self,
+        f: &F,
+        lock_type: &LockType,
+        blocking: bool,
+        func: Func,
+    ) -> Result<(), Error> {
+        self.reserve(f, lock_type, blocking)?;
+        let res = func();
+        if res.is_err() {
+            forget(f);
+        }
+        res
+    }
+}
+impl<B: LockBackend> LockBackend for Registered<B> {
+    fn acquire<F: AsFile>(&self, f: &F, lock_type: &LockType) -> Result<(), Error> {
+        self.take(f, lock_type, true, || self.inner.acquire(f, lock_type))
+    }
+    fn try_acquire<F: AsFile>(&self, f: &F, lock_type: &LockType) -> Result<(), Error> {
+        self.take(f, lock_type, false, || self.inner.try_acquire(f, lock_type))
+    }
+    fn release<F: AsFile>(&self, f: &F) -> Result<(), Error> {
+        self.inner.release(f)?;
+        forget(f);
+        Ok(())
+    }
+    fn convert<F: AsFile>(
+        &self,
+        f: &F,
+        from: &LockType,
+        to: &LockType,
+        blocking: bool,
+    ) -> Result<(), Error> {
+        self.reserve(f, to, blocking)?;
+        let res = self.inner.convert(f, from, to, blocking);
+        if res.is_err() {
+            // Failed conversions leave the old lock in place.
+            if let Ok(id) = sys::file_id(f) {
+                record(&mut lock(&registry().0), id, sys::as_raw(f) as usize, from);
+            }
+        }
+        res
+    }
+    fn is_held<F: AsFile>(&self, f: &F) -> Result<bool, Error> {
+        self.inner.is_held(f)
+    }
+}
+
+// (device, inode) -> the handles holding a lock on it, and the lock type.
+type Held = HashMap<(u64, u64), Vec<(usize, LockType)>>;
+type Registry = Mutex<Held>;
+
+fn registry() -> &'static (Registry, Condvar) {
+    static REGISTRY: OnceLock<(Registry, Condvar)> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+fn lock(held: &Registry) -> MutexGuard<'_, Held> {
+    held.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn record(held: &mut Held, id: (u64, u64), fd: usize, lock_type: &LockType) {
+    let locks = held.entry(id).or_default();
+    locks.retain(|&(other, _)| other != fd);
+    locks.push((fd, *lock_type));
+}
+
+// By handle alone, since the file may no longer be stat-able by the time it's released.
+fn forget<F: AsFile>(f: &F) {
+    let fd = sys::as_raw(f) as usize;
+    let (held, released) = registry();
+    let mut held = lock(held);
+    held.retain(|_, locks| {
+        locks.retain(|&(other, _)| other != fd);
+        !locks.is_empty()
+    });
+    released.notify_all();
+}
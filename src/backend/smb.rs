@@ -0,0 +1,93 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use super::{DotLock, LockBackend};
+use crate::sys::{self, AsFile};
+use crate::{Error, LockStyle, LockType};
+
+// SMB byte-range locks are mandatory on Windows servers, so locking real file contents would
+// block other clients' reads and writes. A single byte far past the end of any real file gives
+// advisory semantics instead.
+const SENTINEL_OFFSET: u64 = 1 << 62;
+
+/// Locking for SMB/CIFS mounts, where the kernel translates `flock(2)` inconsistently (or not
+/// at all). Takes an `fcntl(2)` record lock on a sentinel byte of the guarded file, which CIFS
+/// forwards to the server as a byte-range lock, and additionally holds a `<path>.lock`
+/// [`DotLock`] while locked exclusively, so clients that only honor lock files see it too.
+/// Shared holders wait for the lock file to disappear.
+///
+/// `Send` and `Sync`: the record lock belongs to the process and the lock file to nobody.
+#[derive(Debug)]
+pub struct SmbCompat {
+    marker: DotLock,
+    retry_interval: Duration,
+    holds_marker: AtomicBool,
+}
+impl Clone for SmbCompat {
+    /// Clones the configuration only; the clone doesn't hold anything.
+    fn clone(&self) -> Self {
+        SmbCompat {
+            marker: self.marker.clone(),
+            retry_interval: self.retry_interval,
+            holds_marker: AtomicBool::new(false),
+        }
+    }
+}
+impl SmbCompat {
+    /// `path` is the guarded file; the lock file is created next to it.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        let mut marker = path.as_ref().as_os_str().to_owned();
+        marker.push(".lock");
+        SmbCompat {
+            marker: DotLock::new(marker),
+            retry_interval: Duration::from_millis(100),
+            holds_marker: AtomicBool::new(false),
+        }
+    }
+    /// See [`DotLock::stale_after`].
+    pub fn stale_after(mut self, age: Duration) -> Self {
+        self.marker = self.marker.stale_after(age);
+        self
+    }
+    /// How long a blocking acquisition sleeps between attempts. Defaults to 100ms.
+    pub fn retry_interval(mut self, interval: Duration) -> Self {
+        self.retry_interval = interval;
+        self.marker = self.marker.retry_interval(interval);
+        self
+    }
+    pub fn lock_file(&self) -> &Path {
+        self.marker.path()
+    }
+}
+impl LockBackend for SmbCompat {
+    fn acquire<F: AsFile>(&self, f: &F, lock_type: &LockType) -> Result<(), Error> {
+        loop {
+            match self.try_acquire(f, lock_type) {
+                Err(Error::WouldBlock) => std::thread::sleep(self.retry_interval),
+                res => return res,
+            }
+        }
+    }
+    fn try_acquire<F: AsFile>(&self, f: &F, lock_type: &LockType) -> Result<(), Error> {
+        sys::lock_range(f, &LockStyle::Fcntl, lock_type, SENTINEL_OFFSET, 1, false)?;
+        let res = match lock_type {
+            LockType::Exclusive => self
+                .marker
+                .try_acquire(f, lock_type)
+                .map(|()| self.holds_marker.store(true, Ordering::Release)),
+            LockType::Shared if self.marker.path().exists() => Err(Error::WouldBlock),
+            LockType::Shared => Ok(()),
+        };
+        if res.is_err() {
+            let _ = sys::unlock_range(f, &LockStyle::Fcntl, SENTINEL_OFFSET, 1, false);
+        }
+        res
+    }
+    fn release<F: AsFile>(&self, f: &F) -> Result<(), Error> {
+        if self.holds_marker.swap(false, Ordering::AcqRel) {
+            self.marker.release(f)?;
+        }
+        sys::unlock_range(f, &LockStyle::Fcntl, SENTINEL_OFFSET, 1, false)
+    }
+}
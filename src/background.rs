@@ -0,0 +1,107 @@
+use std::mem::ManuallyDrop;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+
+use crate::backend::LockBackend;
+use crate::{AsFile, FdLock, LockStyle};
+
+/// An [`FdLock`] for async code: dropping it hands the lock to a background thread to release,
+/// so an aborted task never runs a possibly blocking release on the executor. Create one with
+/// [`FdLock::into_background`].
+pub struct AsyncFdLock<F, B = LockStyle>
+where
+    F: AsFile + Send + 'static,
+    B: LockBackend + Send + 'static,
+{
+    // Only taken out by `into_inner` and `drop`, which are the last uses of the guard.
+    inner: ManuallyDrop<FdLock<F, B>>,
+}
+impl<F, B> AsyncFdLock<F, B>
+where
+    F: AsFile + Send + 'static,
+    B: LockBackend + Send + 'static,
+{
+    /// Turns this back into an [`FdLock`] that releases in place.
+    pub fn into_inner(self) -> FdLock<F, B> {
+        let mut this = ManuallyDrop::new(self);
+        unsafe { ManuallyDrop::take(&mut this.inner) }
+    }
+}
+impl<F, B> std::ops::Deref for AsyncFdLock<F, B>
+where
+    F: AsFile + Send + 'static,
+    B: LockBackend + Send + 'static,
+{
+    type Target = F;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+impl<F, B> std::ops::DerefMut for AsyncFdLock<F, B>
+where
+    F: AsFile + Send + 'static,
+    B: LockBackend + Send + 'static,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+impl<F, B> std::ops::Drop for AsyncFdLock<F, B>
+where
+    F: AsFile + Send + 'static,
+    B: LockBackend + Send + 'static,
+{
+    fn drop(&mut self) {
+        let lock = unsafe { ManuallyDrop::take(&mut self.inner) };
+        release_in_background(Box::new(lock))
+    }
+}
+
+impl<F, B> FdLock<F, B>
+where
+    F: AsFile + Send + 'static,
+    B: LockBackend + Send + 'static,
+{
+    /// Converts this into an [`AsyncFdLock`], which releases on a background thread when
+    /// dropped.
+    pub fn into_background(self) -> AsyncFdLock<F, B> {
+        AsyncFdLock {
+            inner: ManuallyDrop::new(self),
+        }
+    }
+}
+
+// A single thread, started on first use, drops the locks it's sent. A failed release goes to
+// the guard's `OnDropError` there, and if that panics the thread carries on. Should the thread
+// fail to start, the lock is released in place and the next one tries again.
+fn release_in_background(lock: Box<dyn Send>) {
+    static RELEASER: Mutex<Option<Sender<Box<dyn Send>>>> = Mutex::new(None);
+    let lock = {
+        let mut releaser = RELEASER.lock().unwrap_or_else(|e| e.into_inner());
+        if releaser.is_none() {
+            *releaser = start_releaser();
+        }
+        match &*releaser {
+            Some(releaser) => match releaser.send(lock) {
+                Ok(()) => return,
+                Err(mpsc::SendError(lock)) => lock,
+            },
+            None => lock,
+        }
+    };
+    drop(lock);
+}
+
+fn start_releaser() -> Option<Sender<Box<dyn Send>>> {
+    let (send, recv) = mpsc::channel::<Box<dyn Send>>();
+    std::thread::Builder::new()
+        .name("fd-lock-release".into())
+        .spawn(move || {
+            for lock in recv {
+                let _ = panic::catch_unwind(AssertUnwindSafe(move || drop(lock)));
+            }
+        })
+        .ok()?;
+    Some(send)
+}
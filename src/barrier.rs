@@ -0,0 +1,81 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::{sys, Error, FdLock, LockType};
+
+/// A barrier for `n` processes: each calls [`wait`](FileBarrier::wait), and all of them return
+/// once the `n`th has arrived. Usable again afterwards for the next round.
+///
+/// The file at `path` holds a generation number and how many have arrived in it, changed
+/// under an exclusive lock; waiters poll (backing off up to 50ms) for the generation to move
+/// on. Every participant must pass the same `n`, and a process that dies between arriving and
+/// the barrier opening still counts as arrived.
+#[derive(Debug)]
+pub struct FileBarrier {
+    f: File,
+    n: u64,
+}
+impl FileBarrier {
+    /// Opens (creating if needed) the file at `path`. Zero `n` fails with
+    /// [`Error::InvalidOperation`].
+    pub fn new<P: AsRef<Path>>(path: P, n: u64) -> Result<Self, Error> {
+        if n == 0 {
+            return Err(Error::InvalidOperation);
+        }
+        let f = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(sys::io_error)?;
+        Ok(FileBarrier { f, n })
+    }
+    pub fn n(&self) -> u64 {
+        self.n
+    }
+    /// Arrives and waits for the rest. Returns `true` in exactly one of the processes, the
+    /// last to arrive.
+    pub fn wait(&self) -> Result<bool, Error> {
+        let generation = {
+            let lock = FdLock::lock(&self.f, LockType::Exclusive, true)?;
+            let (generation, arrived) = read(&lock)?;
+            if arrived + 1 >= self.n {
+                write(&lock, generation.wrapping_add(1), 0)?;
+                return Ok(true);
+            }
+            write(&lock, generation, arrived + 1)?;
+            generation
+        };
+        let mut wait = Duration::from_millis(1);
+        loop {
+            std::thread::sleep(wait);
+            let lock = FdLock::lock(&self.f, LockType::Shared, true)?;
+            if read(&lock)?.0 != generation {
+                return Ok(false);
+            }
+            wait = (wait * 2).min(Duration::from_millis(50));
+        }
+    }
+}
+
+// "<generation> <arrived>\n"; an empty file is generation 0 with no one arrived.
+fn read(mut f: &File) -> Result<(u64, u64), Error> {
+    let mut contents = String::new();
+    f.seek(SeekFrom::Start(0)).map_err(sys::io_error)?;
+    f.read_to_string(&mut contents).map_err(sys::io_error)?;
+    let mut fields = contents.split_whitespace().map(str::parse);
+    match (fields.next(), fields.next()) {
+        (None, _) => Ok((0, 0)),
+        (Some(Ok(generation)), Some(Ok(arrived))) => Ok((generation, arrived)),
+        _ => Err(Error::InvalidOperation),
+    }
+}
+
+fn write(mut f: &File, generation: u64, arrived: u64) -> Result<(), Error> {
+    f.set_len(0).map_err(sys::io_error)?;
+    f.seek(SeekFrom::Start(0)).map_err(sys::io_error)?;
+    writeln!(f, "{} {}", generation, arrived).map_err(sys::io_error)
+}
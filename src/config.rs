@@ -0,0 +1,156 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::lockfile::lock_path;
+use crate::{atomic, sys, Error, FdLock, LockType};
+
+type DataError = Box<dyn std::error::Error + Send + Sync>;
+
+/// How a [`LockedConfig`] file is written. Each format needs its feature (`json`, `toml`,
+/// `yaml`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Format {
+    #[cfg(feature = "json")]
+    Json,
+    #[cfg(feature = "toml")]
+    Toml,
+    #[cfg(feature = "yaml")]
+    Yaml,
+}
+impl Format {
+    fn parse<T: DeserializeOwned>(self, contents: &[u8]) -> Result<T, DataError> {
+        match self {
+            #[cfg(feature = "json")]
+            Format::Json => Ok(serde_json::from_slice(contents)?),
+            #[cfg(feature = "toml")]
+            Format::Toml => Ok(toml::from_str(std::str::from_utf8(contents)?)?),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => Ok(serde_yaml::from_slice(contents)?),
+        }
+    }
+    fn print<T: Serialize>(self, value: &T) -> Result<Vec<u8>, DataError> {
+        match self {
+            #[cfg(feature = "json")]
+            Format::Json => Ok(serde_json::to_vec_pretty(value)?),
+            #[cfg(feature = "toml")]
+            Format::Toml => Ok(toml::to_string_pretty(value)?.into_bytes()),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => Ok(serde_yaml::to_string(value)?.into_bytes()),
+        }
+    }
+}
+
+/// A config file that several programs read and edit: reads take a shared lock, and writes an
+/// exclusive one, replacing the file in one step (see [`atomic_write`](crate::atomic_write))
+/// so a reader never sees it half written.
+///
+/// The file is created, empty, on first use. Every program editing it should go through
+/// `LockedConfig` (or the other path-based helpers) for the locking to hold.
+pub struct LockedConfig<T> {
+    path: PathBuf,
+    format: Format,
+    value: PhantomData<fn() -> T>,
+}
+impl<T: Serialize + DeserializeOwned> LockedConfig<T> {
+    pub fn open<P: AsRef<Path>>(path: P, format: Format) -> Self {
+        LockedConfig {
+            path: path.as_ref().to_owned(),
+            format,
+            value: PhantomData,
+        }
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    pub fn format(&self) -> Format {
+        self.format
+    }
+    /// Reads the config under a shared lock. `None` if the file is empty.
+    pub fn read(&self) -> Result<Option<T>, ConfigError> {
+        self.load(LockType::Shared).map(|(value, _)| value)
+    }
+    /// Replaces the config with `value`, under an exclusive lock.
+    pub fn write(&self, value: &T) -> Result<(), ConfigError> {
+        let lock = lock_path(&self.path, LockType::Exclusive, true)?;
+        self.save(value)?;
+        lock.unlock().map_err(Error::from)?;
+        Ok(())
+    }
+    /// Reads the config, lets `func` change it, and writes it back, all under one exclusive
+    /// lock so no other program's edit can be lost in between. An empty file starts out as
+    /// `T::default()`.
+    pub fn modify<R, Func: FnOnce(&mut T) -> R>(&self, func: Func) -> Result<R, ConfigError>
+    where
+        T: Default,
+    {
+        let (value, lock) = self.load(LockType::Exclusive)?;
+        let mut value = value.unwrap_or_default();
+        let res = func(&mut value);
+        self.save(&value)?;
+        lock.unlock().map_err(Error::from)?;
+        Ok(res)
+    }
+    fn load(&self, lock_type: LockType) -> Result<(Option<T>, FdLock<File>), ConfigError> {
+        let mut lock = lock_path(&self.path, lock_type, true)?;
+        let mut contents = Vec::new();
+        lock.read_to_end(&mut contents).map_err(sys::io_error)?;
+        if contents.iter().all(u8::is_ascii_whitespace) {
+            return Ok((None, lock));
+        }
+        let value = self.format.parse(&contents).map_err(ConfigError::Data)?;
+        Ok((Some(value), lock))
+    }
+    fn save(&self, value: &T) -> Result<(), ConfigError> {
+        let contents = self.format.print(value).map_err(ConfigError::Data)?;
+        atomic::replace(&self.path, |f| {
+            f.write_all(&contents).map_err(sys::io_error)
+        })?;
+        Ok(())
+    }
+}
+impl<T> fmt::Debug for LockedConfig<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LockedConfig")
+            .field("path", &self.path)
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+/// Why a [`LockedConfig`] operation failed.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// Locking, reading or writing the file failed.
+    Lock(Error),
+    /// The contents weren't valid for the format and type, or the value couldn't be written in
+    /// the format.
+    Data(DataError),
+}
+impl From<Error> for ConfigError {
+    fn from(e: Error) -> Self {
+        ConfigError::Lock(e)
+    }
+}
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Lock(e) => e.fmt(f),
+            ConfigError::Data(e) => write!(f, "Invalid config: {}", e),
+        }
+    }
+}
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Lock(e) => Some(e),
+            ConfigError::Data(e) => Some(&**e),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use std::fs::File;
+use std::path::Path;
+
+use crate::{sys, Error, FdLock, LockStyle, LockType, UnlockError};
+
+/// A lock on a directory, from [`FdLock::lock_dir`], for coordinating access to everything in
+/// it (a cache, say). Files in the directory can be opened relative to the locked descriptor,
+/// so they are found in the directory that was locked even if it has since been renamed.
+///
+/// Like any advisory lock, it only keeps out processes that lock the directory too.
+#[derive(Debug)]
+pub struct DirLock(FdLock<File>);
+
+impl FdLock<File> {
+    /// Opens the directory at `path` (`O_DIRECTORY`) and locks it.
+    ///
+    /// A directory can only be opened for reading, and [`LockStyle::Fcntl`] (the default on
+    /// Solaris and illumos) needs a descriptor open for writing to lock exclusively; there only
+    /// shared directory locks work.
+    pub fn lock_dir<P: AsRef<Path>>(
+        path: P,
+        lock_type: LockType,
+        blocking: bool,
+    ) -> Result<DirLock, Error> {
+        let dir = sys::open_dir(path.as_ref())?;
+        FdLock::lock_with(dir, LockStyle::default(), lock_type, blocking).map(DirLock)
+    }
+}
+impl DirLock {
+    /// Opens `path`, relative to the directory, for reading.
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<File, Error> {
+        self.open_with(path, libc::O_RDONLY)
+    }
+    /// Opens `path`, relative to the directory, for writing, creating or truncating it.
+    pub fn create<P: AsRef<Path>>(&self, path: P) -> Result<File, Error> {
+        self.open_with(path, libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC)
+    }
+    /// Opens `path`, relative to the directory, with `openat(2)` flags (`O_CLOEXEC` is always
+    /// added). New files get mode `0o666`, less the umask.
+    pub fn open_with<P: AsRef<Path>>(&self, path: P, flags: libc::c_int) -> Result<File, Error> {
+        sys::open_at(sys::as_raw(&self.0), path.as_ref(), flags)
+    }
+    /// Opens and locks `path`, relative to the directory; see [`FdLock::open_locked_at`].
+    pub fn open_locked<P: AsRef<Path>>(
+        &self,
+        path: P,
+        lock_type: LockType,
+        blocking: bool,
+    ) -> Result<FdLock<File>, Error> {
+        FdLock::open_locked_at(&self.0, path, lock_type, blocking)
+    }
+    /// Removes the file `path`, relative to the directory.
+    pub fn remove_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        sys::unlink_at(sys::as_raw(&self.0), path.as_ref())
+    }
+    pub fn lock_type(&self) -> LockType {
+        self.0.lock_type()
+    }
+    /// The lock on the directory's descriptor.
+    pub fn lock(&self) -> &FdLock<File> {
+        &self.0
+    }
+    pub fn into_inner(self) -> FdLock<File> {
+        self.0
+    }
+    /// Releases the lock and returns the directory's descriptor.
+    pub fn unlock(self) -> Result<File, UnlockError<File>> {
+        self.0.unlock()
+    }
+}
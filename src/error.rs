@@ -0,0 +1,183 @@
+use std::io::{Error as IOError, ErrorKind as IOErrorKind};
+
+use crate::backend::LockBackend;
+use crate::sys::{self, AsFile};
+use crate::{FdLock, LockStyle};
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    InvalidFd,
+    Interrupted,
+    InvalidOperation,
+    OutOfMemory,
+    WouldBlock,
+    Unsupported,
+    /// The lock wasn't acquired before the deadline.
+    Timeout,
+    /// Waiting would deadlock with another process (`EDEADLK`), e.g. two processes both trying
+    /// to upgrade a shared fcntl(2) lock.
+    Deadlock,
+    /// The file or filesystem isn't set up for the requested kind of lock.
+    Misconfigured(&'static str),
+    /// This process already holds a conflicting lock on the file through another handle; see
+    /// [`Registered`](crate::backend::Registered).
+    SelfConflict,
+    /// Any other error from the OS, the same type whichever backend made the call.
+    Other(IOError),
+}
+/// The variant of an [`Error`], without its payload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    InvalidFd,
+    Interrupted,
+    InvalidOperation,
+    OutOfMemory,
+    WouldBlock,
+    Unsupported,
+    Timeout,
+    Deadlock,
+    Misconfigured,
+    SelfConflict,
+    Other,
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::InvalidFd => ErrorKind::InvalidFd,
+            Error::Interrupted => ErrorKind::Interrupted,
+            Error::InvalidOperation => ErrorKind::InvalidOperation,
+            Error::OutOfMemory => ErrorKind::OutOfMemory,
+            Error::WouldBlock => ErrorKind::WouldBlock,
+            Error::Unsupported => ErrorKind::Unsupported,
+            Error::Timeout => ErrorKind::Timeout,
+            Error::Deadlock => ErrorKind::Deadlock,
+            Error::Misconfigured(_) => ErrorKind::Misconfigured,
+            Error::SelfConflict => ErrorKind::SelfConflict,
+            Error::Other(_) => ErrorKind::Other,
+        }
+    }
+    /// The OS error number (errno, or the Windows error code) behind this error, if it came
+    /// from the OS.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        sys::raw_os_error(self)
+    }
+}
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use Error::*;
+        match self {
+            InvalidFd => write!(f, "The provided item is not an open file descriptor."),
+            Interrupted => write!(f, "While waiting to acquire a lock, the call was interrupted by delivery of a signal caught by a handler."),
+            InvalidOperation => write!(f, "File locking operation is invalid."),
+            OutOfMemory => write!(f, "The kernel ran out of memory for allocating lock records."),
+            WouldBlock => write!(f, "The file is locked and the blocking flag was set to false."),
+            Unsupported => write!(f, "File locking is not supported on this platform."),
+            Timeout => write!(f, "Timed out waiting for the file lock."),
+            Deadlock => write!(f, "Waiting for the file lock would deadlock."),
+            Misconfigured(reason) => write!(f, "File is not configured for this lock: {}.", reason),
+            SelfConflict => write!(f, "The file is already locked through another handle in this process."),
+            Other(e) => write!(f, "Non-flock error: {}", e),
+        }
+    }
+}
+impl std::error::Error for Error {}
+// Not derived, since `std::io::Error` is neither.
+impl Clone for Error {
+    fn clone(&self) -> Self {
+        match self {
+            Error::InvalidFd => Error::InvalidFd,
+            Error::Interrupted => Error::Interrupted,
+            Error::InvalidOperation => Error::InvalidOperation,
+            Error::OutOfMemory => Error::OutOfMemory,
+            Error::WouldBlock => Error::WouldBlock,
+            Error::Unsupported => Error::Unsupported,
+            Error::Timeout => Error::Timeout,
+            Error::Deadlock => Error::Deadlock,
+            Error::Misconfigured(reason) => Error::Misconfigured(reason),
+            Error::SelfConflict => Error::SelfConflict,
+            Error::Other(e) => Error::Other(match e.raw_os_error() {
+                Some(code) => IOError::from_raw_os_error(code),
+                None => IOError::new(e.kind(), e.to_string()),
+            }),
+        }
+    }
+}
+/// `Other` errors compare by their OS error number, or by their [`std::io::ErrorKind`] when
+/// neither has one.
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Error::Misconfigured(a), Error::Misconfigured(b)) => a == b,
+            (Error::Other(a), Error::Other(b)) => match (a.raw_os_error(), b.raw_os_error()) {
+                (None, None) => a.kind() == b.kind(),
+                (a, b) => a == b,
+            },
+            _ => self.kind() == other.kind(),
+        }
+    }
+}
+impl From<Error> for IOError {
+    fn from(e: Error) -> IOError {
+        use Error::*;
+        match e {
+            InvalidFd | InvalidOperation => IOError::new(IOErrorKind::InvalidInput, e),
+            Interrupted => IOError::new(IOErrorKind::Interrupted, e),
+            Other(_) => match e.raw_os_error() {
+                Some(code) => IOError::from_raw_os_error(code),
+                None => IOError::other(e),
+            },
+            OutOfMemory | Misconfigured(_) => IOError::other(e),
+            WouldBlock => IOError::new(IOErrorKind::WouldBlock, e),
+            Timeout => IOError::new(IOErrorKind::TimedOut, e),
+            Deadlock | SelfConflict => IOError::new(IOErrorKind::Deadlock, e),
+            Unsupported => IOError::new(IOErrorKind::Unsupported, e),
+        }
+    }
+}
+
+/// A failed [`FdLock::unlock`]. Holds on to the guard, which still has the lock.
+pub struct UnlockError<F: AsFile, B: LockBackend = LockStyle> {
+    lock: FdLock<F, B>,
+    error: Error,
+}
+impl<F: AsFile, B: LockBackend> UnlockError<F, B> {
+    pub(crate) fn new(lock: FdLock<F, B>, error: Error) -> Self {
+        UnlockError { lock, error }
+    }
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+    pub fn into_lock(self) -> FdLock<F, B> {
+        self.lock
+    }
+    pub fn into_parts(self) -> (FdLock<F, B>, Error) {
+        (self.lock, self.error)
+    }
+}
+impl<F: AsFile, B: LockBackend> std::fmt::Debug for UnlockError<F, B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("UnlockError")
+            .field("lock", &self.lock)
+            .field("error", &self.error)
+            .finish()
+    }
+}
+impl<F: AsFile, B: LockBackend> std::fmt::Display for UnlockError<F, B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Failed to release the file lock: {}", self.error)
+    }
+}
+impl<F: AsFile, B: LockBackend> std::error::Error for UnlockError<F, B> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+/// Drops the guard, which tries the release once more.
+impl<F: AsFile, B: LockBackend> From<UnlockError<F, B>> for Error {
+    fn from(e: UnlockError<F, B>) -> Self {
+        e.error
+    }
+}
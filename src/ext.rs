@@ -0,0 +1,26 @@
+use crate::{AsFile, Error, FdLock, FdLockRef, LockType};
+
+/// Shorthands for locking a handle in place with the default
+/// [`LockStyle`](crate::LockStyle), in the style of `fs2`. The guards borrow the handle.
+///
+/// The names avoid the inherent `lock_shared` and `try_lock_shared` methods `File` has had
+/// since Rust 1.89, which would take precedence and lock without a guard.
+pub trait FlockExt: AsFile + Sized {
+    /// Waits for an exclusive lock.
+    fn flock_exclusive(&self) -> Result<FdLockRef<'_, Self>, Error> {
+        FdLock::lock_ref(self, LockType::Exclusive, true)
+    }
+    /// Waits for a shared lock.
+    fn flock_shared(&self) -> Result<FdLockRef<'_, Self>, Error> {
+        FdLock::lock_ref(self, LockType::Shared, true)
+    }
+    /// Takes an exclusive lock if it's free, or returns `None`.
+    fn try_flock_exclusive(&self) -> Result<Option<FdLockRef<'_, Self>>, Error> {
+        FdLock::try_lock(self, LockType::Exclusive)
+    }
+    /// Takes a shared lock unless an exclusive one is held, or returns `None`.
+    fn try_flock_shared(&self) -> Result<Option<FdLockRef<'_, Self>>, Error> {
+        FdLock::try_lock(self, LockType::Shared)
+    }
+}
+impl<T: AsFile> FlockExt for T {}
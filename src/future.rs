@@ -0,0 +1,175 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use crate::backend::LockBackend;
+use crate::{AsFile, Error, FdLock, LockStyle, LockType, RetryPolicy};
+
+/// A future that acquires a lock without tying up a thread: it retries a non-blocking attempt,
+/// waiting between attempts as set by its [`RetryPolicy`] (the default one unless changed).
+/// Works on any executor, and dropping it before it resolves never leaves the lock held.
+///
+/// The waits between attempts don't rely on the executor's timers. Instead one background
+/// thread, `fd-lock-timer`, is started on first use and shared by every pending `LockFuture`
+/// in the process; it only sleeps until the next wake-up is due and wakes the future then. If
+/// the thread can't be started, the future fails with the error that caused it.
+pub struct LockFuture<F: AsFile, B: LockBackend = LockStyle> {
+    inner: Option<(F, B, LockType)>,
+    policy: RetryPolicy,
+    failures: u32,
+    deadline: Option<Instant>,
+}
+impl<F: AsFile> LockFuture<F> {
+    /// Locks `f` with the default [`LockStyle`].
+    pub fn new(f: F, lock_type: LockType) -> Self {
+        Self::with_backend(f, LockStyle::default(), lock_type)
+    }
+}
+impl<F: AsFile, B: LockBackend> LockFuture<F, B> {
+    pub fn with_backend(f: F, backend: B, lock_type: LockType) -> Self {
+        LockFuture {
+            inner: Some((f, backend, lock_type)),
+            policy: RetryPolicy::default(),
+            failures: 0,
+            deadline: None,
+        }
+    }
+    /// Waits `initial` after the first failed attempt, doubling the wait after each further
+    /// failure up to `max`.
+    pub fn backoff(self, initial: Duration, max: Duration) -> Self {
+        self.retry_policy(RetryPolicy::Exponential { initial, max })
+    }
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+    /// Gives up with [`Error::Timeout`] if the lock hasn't been acquired by `deadline`. Attempts
+    /// are only ever made inside `poll`, so once the future has resolved to `Timeout` the lock
+    /// can't be acquired behind the caller's back.
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+    /// Like [`LockFuture::deadline`], `timeout` from now.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.deadline(Instant::now() + timeout)
+    }
+}
+impl<F: AsFile> FdLock<F> {
+    /// Locks `f` with the default [`LockStyle`], giving up with [`Error::Timeout`] after
+    /// `timeout`. Needs no async runtime; see [`LockFuture`].
+    pub fn lock_async_timeout(f: F, lock_type: LockType, timeout: Duration) -> LockFuture<F> {
+        LockFuture::new(f, lock_type).timeout(timeout)
+    }
+}
+
+// Nothing is pinned structurally.
+impl<F: AsFile, B: LockBackend> Unpin for LockFuture<F, B> {}
+impl<F: AsFile, B: LockBackend> Future for LockFuture<F, B> {
+    type Output = Result<FdLock<F, B>, Error>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let (f, backend, lock_type) = this.inner.as_ref().expect("polled after completion");
+        match backend.try_acquire(f, lock_type) {
+            Ok(()) => {
+                let (f, backend, lock_type) = this.inner.take().unwrap();
+                Poll::Ready(Ok(FdLock::assume_locked(f, backend, lock_type)))
+            }
+            Err(Error::WouldBlock) => {
+                let now = Instant::now();
+                this.failures += 1;
+                let mut at = now + this.policy.delay(this.failures);
+                if let Some(deadline) = this.deadline {
+                    if now >= deadline {
+                        this.inner = None;
+                        return Poll::Ready(Err(Error::Timeout));
+                    }
+                    at = std::cmp::min(at, deadline);
+                }
+                if let Err(e) = wake_at(at, cx.waker().clone()) {
+                    this.inner = None;
+                    return Poll::Ready(Err(e));
+                }
+                Poll::Pending
+            }
+            Err(e) => {
+                this.inner = None;
+                Poll::Ready(Err(e))
+            }
+        }
+    }
+}
+
+struct Timer {
+    at: Instant,
+    waker: Waker,
+}
+impl PartialEq for Timer {
+    fn eq(&self, other: &Self) -> bool {
+        self.at == other.at
+    }
+}
+impl Eq for Timer {}
+impl PartialOrd for Timer {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Timer {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.at.cmp(&other.at)
+    }
+}
+
+// A single timer thread, shared by every pending `LockFuture` and `AcquireStream`, wakes each
+// one when its backoff has elapsed. Fails only if the thread has to be started and can't be,
+// in which case the next call tries again.
+pub(crate) fn wake_at(at: Instant, waker: Waker) -> Result<(), Error> {
+    static TIMERS: Mutex<Option<Sender<Timer>>> = Mutex::new(None);
+    let mut timers = TIMERS.lock().unwrap_or_else(|e| e.into_inner());
+    let timers = match &mut *timers {
+        Some(timers) => timers,
+        none => none.insert(start_timers()?),
+    };
+    // The thread outlives every sender but this one, and survives wakers that panic, so it's
+    // always there to receive.
+    timers
+        .send(Timer { at, waker })
+        .map_err(|_| Error::Interrupted)
+}
+
+fn start_timers() -> Result<Sender<Timer>, Error> {
+    let (send, recv) = mpsc::channel::<Timer>();
+    std::thread::Builder::new()
+        .name("fd-lock-timer".into())
+        .spawn(move || {
+            let mut pending: BinaryHeap<Reverse<Timer>> = BinaryHeap::new();
+            loop {
+                let now = Instant::now();
+                while let Some(Reverse(timer)) = pending.peek() {
+                    if timer.at > now {
+                        break;
+                    }
+                    let Reverse(timer) = pending.pop().unwrap();
+                    let _ = panic::catch_unwind(AssertUnwindSafe(|| timer.waker.wake()));
+                }
+                let next = match pending.peek() {
+                    Some(Reverse(timer)) => recv.recv_timeout(timer.at - now),
+                    None => recv.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+                match next {
+                    Ok(timer) => pending.push(Reverse(timer)),
+                    Err(RecvTimeoutError::Timeout) => (),
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+        })
+        .map_err(crate::sys::io_error)?;
+    Ok(send)
+}
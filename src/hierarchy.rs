@@ -0,0 +1,59 @@
+use std::fs::File;
+use std::path::Path;
+
+use crate::{DirLock, Error, FdLock, LockType};
+
+/// Locks a path and some of its ancestor directories: `paths` lists them, in any order, and
+/// must form a chain, each one inside the next shorter one. The ancestors get shared
+/// directory locks and the deepest path an exclusive lock (a directory lock if it is one,
+/// otherwise it is opened, and created if needed, as a file).
+///
+/// Locks are always taken from the top down, so callers locking overlapping parts of a tree
+/// this way don't deadlock: while one holds `/cache/pkg` exclusively, others can work elsewhere
+/// under `/cache`, and a caller locking `/cache` itself exclusively waits for all of them.
+/// Paths are compared as given, without resolving symlinks or `..`. Anything other than a
+/// chain fails with [`Error::InvalidOperation`].
+pub fn lock_hierarchy<P: AsRef<Path>>(paths: &[P]) -> Result<HierarchyLock, Error> {
+    let mut paths: Vec<&Path> = paths.iter().map(AsRef::as_ref).collect();
+    paths.sort_by_key(|path| path.components().count());
+    let (leaf_path, ancestor_paths) = match paths.split_last() {
+        Some(split) => split,
+        None => return Err(Error::InvalidOperation),
+    };
+    for pair in paths.windows(2) {
+        if pair[0] == pair[1] || !pair[1].starts_with(pair[0]) {
+            return Err(Error::InvalidOperation);
+        }
+    }
+    // Any already in `ancestors` are released when it's dropped.
+    let mut ancestors = Vec::with_capacity(ancestor_paths.len());
+    for path in ancestor_paths {
+        ancestors.push(FdLock::lock_dir(path, LockType::Shared, true)?);
+    }
+    let leaf = if leaf_path.is_dir() {
+        FdLock::lock_dir(leaf_path, LockType::Exclusive, true)?.into_inner()
+    } else {
+        FdLock::open_locked(leaf_path, LockType::Exclusive, true)?
+    };
+    Ok(HierarchyLock { leaf, ancestors })
+}
+
+/// The locks taken by [`lock_hierarchy`]. Dropping it releases the leaf first, then the ancestors.
+#[derive(Debug)]
+pub struct HierarchyLock {
+    leaf: FdLock<File>,
+    ancestors: Vec<DirLock>,
+}
+impl HierarchyLock {
+    /// The exclusive lock on the deepest path.
+    pub fn leaf(&self) -> &FdLock<File> {
+        &self.leaf
+    }
+    pub fn leaf_mut(&mut self) -> &mut FdLock<File> {
+        &mut self.leaf
+    }
+    /// The shared locks on the ancestors, from the top down.
+    pub fn ancestors(&self) -> &[DirLock] {
+        &self.ancestors
+    }
+}
@@ -0,0 +1,94 @@
+use std::fmt;
+use std::os::unix::io::{FromRawFd, IntoRawFd, RawFd};
+use std::str::FromStr;
+
+use crate::{AsFile, Error, FdLock, LockStyle, LockType};
+
+/// A lock left held on a descriptor that survives exec(2), for handing a lock to a re-executed
+/// copy of the program. Create one with [`FdLock::hold_across_exec`], pass its string form
+/// (`<fd>:<type>:<style>`, e.g. `3:exclusive:flock`) to the new program, e.g. in an
+/// environment variable, and turn it back into a guard there with [`InheritedLock::adopt`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InheritedLock {
+    fd: RawFd,
+    lock_type: LockType,
+    style: LockStyle,
+}
+impl InheritedLock {
+    pub fn fd(&self) -> RawFd {
+        self.fd
+    }
+    pub fn lock_type(&self) -> LockType {
+        self.lock_type
+    }
+    pub fn style(&self) -> LockStyle {
+        self.style
+    }
+    /// Rebuilds the guard in the new program.
+    ///
+    /// # Safety
+    ///
+    /// The descriptor must have been inherited from the program that created this, and not be
+    /// owned by anything else.
+    pub unsafe fn adopt<F: AsFile + FromRawFd>(self) -> FdLock<F> {
+        FdLock::adopt_with(F::from_raw_fd(self.fd), self.style, self.lock_type)
+    }
+}
+impl fmt::Display for InheritedLock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lock_type = match self.lock_type {
+            LockType::Exclusive => "exclusive",
+            LockType::Shared => "shared",
+        };
+        let style = match self.style {
+            LockStyle::Flock => "flock",
+            LockStyle::Fcntl => "fcntl",
+            LockStyle::Ofd => "ofd",
+        };
+        write!(f, "{}:{}:{}", self.fd, lock_type, style)
+    }
+}
+impl FromStr for InheritedLock {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let mut parts = s.split(':');
+        let fd = parts.next().and_then(|fd| fd.parse().ok());
+        let lock_type = match parts.next() {
+            Some("exclusive") => Some(LockType::Exclusive),
+            Some("shared") => Some(LockType::Shared),
+            _ => None,
+        };
+        let style = match parts.next() {
+            Some("flock") => Some(LockStyle::Flock),
+            Some("fcntl") => Some(LockStyle::Fcntl),
+            Some("ofd") => Some(LockStyle::Ofd),
+            _ => None,
+        };
+        match (fd, lock_type, style, parts.next()) {
+            (Some(fd), Some(lock_type), Some(style), None) => Ok(InheritedLock {
+                fd,
+                lock_type,
+                style,
+            }),
+            _ => Err(Error::InvalidOperation),
+        }
+    }
+}
+
+impl<F: AsFile + IntoRawFd> FdLock<F> {
+    /// Clears close-on-exec and gives up the guard without releasing the lock, so a program
+    /// started with exec(2) inherits both. Anything else this process spawns inherits the
+    /// descriptor too.
+    pub fn hold_across_exec(self) -> Result<InheritedLock, (Self, Error)> {
+        if let Err(e) = self.set_cloexec(false) {
+            return Err((self, e));
+        }
+        let style = *self.backend();
+        let (fd, lock_type) = self.into_raw_parts();
+        Ok(InheritedLock {
+            fd,
+            lock_type,
+            style,
+        })
+    }
+}
@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+
+use crate::{sys, Error, HolderStatus, Lockfile};
+
+/// Where [`single_instance_with`] puts its lock file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InstanceScope {
+    /// One instance per user: `$XDG_RUNTIME_DIR`, falling back to the temp dir (with the uid
+    /// in the name) on unix; `%LOCALAPPDATA%` on Windows.
+    User,
+    /// One instance per machine: `/run/lock` (or `/var/lock`) on unix; `%ProgramData%` on
+    /// Windows. This usually needs the directory to be writable by everyone running the program.
+    System,
+}
+
+/// What [`single_instance`] found.
+#[derive(Debug)]
+pub enum SingleInstance {
+    /// We are the only instance for as long as this is kept.
+    Acquired(Lockfile),
+    /// Another instance holds the lock.
+    AlreadyRunning {
+        path: PathBuf,
+        /// The other instance's PID, if it has written one yet.
+        pid: Option<u32>,
+    },
+}
+
+/// Makes sure only one copy of the program runs per user, by taking an exclusive
+/// [`Lockfile`] named after `name` without waiting.
+pub fn single_instance(name: &str) -> Result<SingleInstance, Error> {
+    single_instance_with(name, InstanceScope::User)
+}
+
+/// Like [`single_instance`], with an explicit scope. `name` has to be usable as a file name.
+pub fn single_instance_with(name: &str, scope: InstanceScope) -> Result<SingleInstance, Error> {
+    if name.is_empty() || name.contains(['/', '\\']) {
+        return Err(Error::InvalidOperation);
+    }
+    let path = match scope {
+        InstanceScope::User => sys::user_lock_path(name),
+        InstanceScope::System => sys::system_lock_path(name),
+    };
+    match Lockfile::create(&path) {
+        Ok(lock) => Ok(SingleInstance::Acquired(lock)),
+        Err(Error::WouldBlock) => {
+            let pid = match Lockfile::holder_alive(&path)? {
+                HolderStatus::Alive(pid) | HolderStatus::Dead(pid) => Some(pid),
+                HolderStatus::Missing | HolderStatus::NoPid => None,
+            };
+            Ok(SingleInstance::AlreadyRunning { path, pid })
+        }
+        Err(e) => Err(e),
+    }
+}
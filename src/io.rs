@@ -0,0 +1,101 @@
+use std::io::{
+    BufReader, BufWriter, IoSlice, IoSliceMut, Read, Result as IOResult, Seek, SeekFrom, Write,
+};
+
+use crate::backend::LockBackend;
+use crate::{AsFile, FdLock};
+
+impl<F: AsFile, B: LockBackend> FdLock<F, B> {
+    /// Buffers reads from the locked file. The reader borrows the guard, so the lock stays held
+    /// for as long as it's in use.
+    pub fn buffered_reader(&mut self) -> BufReader<&mut Self>
+    where
+        F: Read,
+    {
+        BufReader::new(self)
+    }
+    /// Buffers writes to the locked file. The writer borrows the guard, so it is flushed when
+    /// dropped, before the lock can be released. Call [`Write::flush`] first to see any write
+    /// error, since the flush on drop ignores it.
+    pub fn buffered_writer(&mut self) -> BufWriter<&mut Self>
+    where
+        F: Write,
+    {
+        BufWriter::new(self)
+    }
+}
+
+impl<F: AsFile + Read, B: LockBackend> Read for FdLock<F, B> {
+    fn read(&mut self, buf: &mut [u8]) -> IOResult<usize> {
+        self.get_mut().read(buf)
+    }
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> IOResult<usize> {
+        self.get_mut().read_vectored(bufs)
+    }
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> IOResult<usize> {
+        self.get_mut().read_to_end(buf)
+    }
+    fn read_to_string(&mut self, buf: &mut String) -> IOResult<usize> {
+        self.get_mut().read_to_string(buf)
+    }
+    fn read_exact(&mut self, buf: &mut [u8]) -> IOResult<()> {
+        self.get_mut().read_exact(buf)
+    }
+}
+impl<F: AsFile + Write, B: LockBackend> Write for FdLock<F, B> {
+    fn write(&mut self, buf: &[u8]) -> IOResult<usize> {
+        self.get_mut().write(buf)
+    }
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> IOResult<usize> {
+        self.get_mut().write_vectored(bufs)
+    }
+    fn write_all(&mut self, buf: &[u8]) -> IOResult<()> {
+        self.get_mut().write_all(buf)
+    }
+    fn flush(&mut self) -> IOResult<()> {
+        self.get_mut().flush()
+    }
+}
+impl<F: AsFile + Seek, B: LockBackend> Seek for FdLock<F, B> {
+    fn seek(&mut self, pos: SeekFrom) -> IOResult<u64> {
+        self.get_mut().seek(pos)
+    }
+    fn stream_position(&mut self) -> IOResult<u64> {
+        self.get_mut().stream_position()
+    }
+}
+
+// Like `&File`, a shared guard can do I/O when a shared reference to the handle can.
+impl<'a, F: AsFile, B: LockBackend> Read for &'a FdLock<F, B>
+where
+    &'a F: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> IOResult<usize> {
+        self.get_ref().read(buf)
+    }
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> IOResult<usize> {
+        self.get_ref().read_vectored(bufs)
+    }
+}
+impl<'a, F: AsFile, B: LockBackend> Write for &'a FdLock<F, B>
+where
+    &'a F: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> IOResult<usize> {
+        self.get_ref().write(buf)
+    }
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> IOResult<usize> {
+        self.get_ref().write_vectored(bufs)
+    }
+    fn flush(&mut self) -> IOResult<()> {
+        self.get_ref().flush()
+    }
+}
+impl<'a, F: AsFile, B: LockBackend> Seek for &'a FdLock<F, B>
+where
+    &'a F: Seek,
+{
+    fn seek(&mut self, pos: SeekFrom) -> IOResult<u64> {
+        self.get_ref().seek(pos)
+    }
+}
@@ -0,0 +1,105 @@
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+use crate::{sys, Error, FdLock, LockFuture, LockType};
+
+/// A candidate in a leader election among processes sharing a lock file: whoever holds the
+/// exclusive lock on it leads. The file is created if needed and left in place.
+#[derive(Clone, Debug)]
+pub struct Leader {
+    path: PathBuf,
+}
+impl Leader {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Leader {
+            path: path.as_ref().to_owned(),
+        }
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// Becomes leader if nobody else is, without waiting.
+    pub fn try_acquire(&self) -> Result<Option<Leadership>, Error> {
+        Ok(FdLock::try_lock(self.open()?, LockType::Exclusive)?.map(Leadership::new))
+    }
+    /// Waits until this candidate is leader.
+    pub fn wait_for_leadership(&self) -> Result<Leadership, Error> {
+        FdLock::lock(self.open()?, LockType::Exclusive, true).map(Leadership::new)
+    }
+    /// Like [`Leader::wait_for_leadership`], without tying up a thread; see [`LockFuture`].
+    pub async fn wait_for_leadership_async(&self) -> Result<Leadership, Error> {
+        LockFuture::new(self.open()?, LockType::Exclusive)
+            .await
+            .map(Leadership::new)
+    }
+    fn open(&self) -> Result<File, Error> {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.path)
+            .map_err(sys::io_error)
+    }
+}
+
+/// Proof of being leader, until dropped or [`resign`](Leadership::resign)ed.
+pub struct Leadership {
+    lock: FdLock<File>,
+    lost: bool,
+    on_lost: Option<Box<dyn FnOnce() + Send>>,
+}
+impl Leadership {
+    fn new(lock: FdLock<File>) -> Self {
+        Leadership {
+            lock,
+            lost: false,
+            on_lost: None,
+        }
+    }
+    /// Sets a hook for [`Leadership::check`] to call, once, when it finds leadership lost.
+    pub fn on_lost<Func: FnOnce() + Send + 'static>(&mut self, hook: Func) {
+        self.on_lost = Some(Box::new(hook));
+    }
+    /// Checks that we are still leader: that the descriptor is still valid and, where
+    /// [`FdLock::verify_still_held`] is supported, that the lock is still there. Returns
+    /// `false` (and stays `false`) once leadership is lost. Worth calling before acting as
+    /// leader, since a lock file on a network filesystem can lose its lock behind our back.
+    pub fn check(&mut self) -> Result<bool, Error> {
+        if self.lost {
+            return Ok(false);
+        }
+        let held = match sys::file_id(self.lock.get_ref()) {
+            Err(Error::InvalidFd) => false,
+            Err(e) => return Err(e),
+            Ok(_) => match self.lock.verify_still_held() {
+                Err(Error::Unsupported) => true,
+                held => held?,
+            },
+        };
+        if !held {
+            self.lost = true;
+            if let Some(hook) = self.on_lost.take() {
+                hook();
+            }
+        }
+        Ok(held)
+    }
+    pub fn lock(&self) -> &FdLock<File> {
+        &self.lock
+    }
+    /// Steps down, reporting any error releasing the lock.
+    pub fn resign(self) -> Result<(), Error> {
+        self.lock.unlock()?;
+        Ok(())
+    }
+}
+impl fmt::Debug for Leadership {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Leadership")
+            .field("lock", &self.lock)
+            .field("lost", &self.lost)
+            .finish()
+    }
+}
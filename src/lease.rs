@@ -0,0 +1,91 @@
+//! Linux file leases (`F_SETLEASE`), which notify the holder when another process opens (or
+//! truncates) the file, giving it a chance to flush state before the open proceeds.
+
+use std::mem::ManuallyDrop;
+
+use crate::sys::{self, AsFile};
+use crate::{Error, LockType, OnDropError};
+
+/// A held lease. `LockType::Shared` is a read lease, broken when another process opens the file
+/// for writing; `LockType::Exclusive` is a write lease, broken by any open. A read lease needs
+/// the file to be open read-only, and a write lease needs it to be owned by the caller (or
+/// `CAP_LEASE`).
+///
+/// When the lease is broken the kernel sends the holder `SIGIO` (or the signal chosen with
+/// [`Lease::notify_signal`]), and the opener is blocked until the lease is released or
+/// `/proc/sys/fs/lease-break-time` elapses. A pending break can also be polled for with
+/// [`Lease::break_pending`]. The default action for `SIGIO` terminates the process, so install
+/// a handler (or ignore it) before taking a lease.
+pub struct Lease<F: AsFile> {
+    inner: ManuallyDrop<F>,
+    lease_type: LockType,
+    on_drop: ManuallyDrop<OnDropError>,
+}
+impl<F: AsFile> std::ops::Deref for Lease<F> {
+    type Target = F;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+impl<F: AsFile> std::ops::DerefMut for Lease<F> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+impl<F: AsFile> Lease<F> {
+    /// Takes the lease. Leases never wait: a conflicting open fails with [`Error::WouldBlock`].
+    pub fn acquire(f: F, lease_type: LockType) -> Result<Self, Error> {
+        sys::set_lease(&f, Some(&lease_type))?;
+        Ok(Lease {
+            inner: ManuallyDrop::new(f),
+            lease_type,
+            on_drop: ManuallyDrop::new(OnDropError::default()),
+        })
+    }
+    /// Delivers break notifications as `signal` instead of `SIGIO`. A real-time signal
+    /// installed with `SA_SIGINFO` also receives the leased descriptor in `si_fd`. Passing 0
+    /// restores `SIGIO`.
+    pub fn notify_signal(&self, signal: i32) -> Result<(), Error> {
+        sys::set_lease_signal(&*self.inner, signal)
+    }
+    /// Whether another process has started breaking the lease, so it should be released (or
+    /// downgraded from a write lease) promptly.
+    pub fn break_pending(&self) -> Result<bool, Error> {
+        // While a break is in progress the kernel reports the type the lease is being
+        // downgraded to.
+        Ok(!matches!(
+            (&self.lease_type, sys::get_lease(&*self.inner)?),
+            (LockType::Exclusive, Some(LockType::Exclusive))
+                | (LockType::Shared, Some(LockType::Shared))
+        ))
+    }
+    /// Sets what dropping the lease does if releasing it fails. Defaults to
+    /// [`OnDropError::Ignore`].
+    pub fn set_on_drop_error(&mut self, on_drop: OnDropError) {
+        *self.on_drop = on_drop;
+    }
+    pub fn release(self) -> Result<F, (Self, Error)> {
+        match sys::set_lease(&*self.inner, None) {
+            Ok(()) => {
+                let mut this = ManuallyDrop::new(self);
+                unsafe {
+                    ManuallyDrop::drop(&mut this.on_drop);
+                    Ok(ManuallyDrop::take(&mut this.inner))
+                }
+            }
+            Err(e) => Err((self, e)),
+        }
+    }
+}
+impl<F: AsFile> std::ops::Drop for Lease<F> {
+    fn drop(&mut self) {
+        let res = sys::set_lease(&*self.inner, None);
+        let on_drop = unsafe {
+            ManuallyDrop::drop(&mut self.inner);
+            ManuallyDrop::take(&mut self.on_drop)
+        };
+        if let Err(e) = res {
+            on_drop.handle(e)
+        }
+    }
+}
@@ -1,120 +1,114 @@
-use std::io::{Error as IOError, ErrorKind as IOErrorKind};
-use std::os::unix::io::AsRawFd;
+mod atomic;
+pub mod backend;
+mod background;
+mod barrier;
+#[cfg(any(feature = "json", feature = "toml", feature = "yaml"))]
+mod config;
+#[cfg(unix)]
+mod dir;
+mod error;
+mod ext;
+mod future;
+#[cfg(unix)]
+mod hierarchy;
+#[cfg(unix)]
+mod inherit;
+mod instance;
+mod io;
+mod leader;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub mod lease;
+mod lock;
+mod lockfile;
+mod maybe;
+mod multi;
+#[cfg(feature = "json")]
+mod mutex;
+mod on_drop;
+mod options;
+mod order;
+pub mod range;
+mod raw;
+mod reentrant;
+#[cfg(unix)]
+mod request;
+mod retry;
+#[cfg(any(feature = "tokio", feature = "async-std", feature = "blocking"))]
+mod rt;
+mod rwlock;
+mod scoped;
+mod semaphore;
+mod service;
+mod shared;
+mod stdio;
+#[cfg(feature = "stream")]
+mod stream;
+mod sys;
+#[cfg(feature = "tokio")]
+mod tokio_file;
+mod typed;
+mod types;
+mod update;
+#[cfg(all(feature = "tokio-uring", target_os = "linux"))]
+mod uring;
+mod wait;
+mod watcher;
+mod writer;
 
-use nix::{
-    errno::Errno,
-    fcntl::{flock, FlockArg},
-    Error as NixError,
-};
+pub use atomic::atomic_write;
+pub use background::AsyncFdLock;
+pub use barrier::FileBarrier;
+#[cfg(any(feature = "json", feature = "toml", feature = "yaml"))]
+pub use config::{ConfigError, Format, LockedConfig};
+#[cfg(unix)]
+pub use dir::DirLock;
+pub use error::{Error, ErrorKind, UnlockError};
+pub use ext::FlockExt;
+pub use future::LockFuture;
+#[cfg(unix)]
+pub use hierarchy::{lock_hierarchy, HierarchyLock};
+#[cfg(unix)]
+pub use inherit::InheritedLock;
+pub use instance::{single_instance, single_instance_with, InstanceScope, SingleInstance};
+pub use leader::{Leader, Leadership};
+pub use lock::{FdLock, FdLockRef, OwnedFdLock};
+pub use lockfile::{HolderStatus, Lockfile};
+pub use maybe::MaybeLocked;
+pub use multi::{lock_all, lock_all_async, lock_all_async_with, lock_all_with, LockSet};
+#[cfg(feature = "json")]
+pub use mutex::{FileMutex, FileMutexError, FileMutexGuard};
+pub use on_drop::OnDropError;
+pub use options::LockOptions;
+pub use order::{set_lock_order_check, LockOrderCheck};
+pub use raw::RawFile;
+pub use reentrant::{Reentrancy, ReentrantFileLock, ReentrantGuard};
+#[cfg(unix)]
+pub use request::LockRequest;
+pub use retry::{OnInterrupt, RetryPolicy};
+pub use rwlock::{FileReadGuard, FileRwLock, FileWriteGuard};
+pub use scoped::{with_lock, with_lock_with};
+pub use semaphore::{FileSemaphore, Permit};
+pub use service::{LockService, PendingLock};
+pub use shared::SharedFdLock;
+pub use stdio::{lock_stderr, lock_stdio, lock_stdout};
+#[cfg(feature = "stream")]
+pub use stream::{AcquireStream, LockEvent};
+#[cfg(feature = "tokio")]
+pub use tokio_file::TokioFdLock;
+pub use typed::{ExclusiveGuard, SharedGuard};
+pub use types::{LockStyle, LockType};
+pub use update::update;
+#[cfg(all(feature = "tokio-uring", target_os = "linux"))]
+pub use uring::{UringFdLock, UringFile};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use wait::set_lock_timeout_signal;
+pub use wait::ContentionInfo;
+pub use watcher::LockWatcher;
+pub use writer::{locked_append, LockedWriter};
 
-pub enum LockType {
-    Exclusive,
-    Shared,
-}
-
-#[derive(Debug)]
-pub enum Error {
-    InvalidFd,
-    Interrupted,
-    InvalidOperation,
-    OutOfMemory,
-    WouldBlock,
-    Other(NixError),
-}
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        use Error::*;
-        match self {
-            InvalidFd => write!(f, "The provided item is not an open file descriptor."),
-            Interrupted => write!(f, "While waiting to acquire a lock, the call was interrupted by delivery of a signal caught by a handler."),
-            InvalidOperation => write!(f, "File locking operation is invalid."),
-            OutOfMemory => write!(f, "The kernel ran out of memory for allocating lock records."),
-            WouldBlock => write!(f, "The file is locked and the blocking flag was set to false."),
-            Other(e) => write!(f, "Non-flock error: {}", e),
-        }
-    }
-}
-impl std::error::Error for Error {}
-impl From<NixError> for Error {
-    fn from(e: NixError) -> Self {
-        match e {
-            Errno::EBADF => Error::InvalidFd,
-            Errno::EINTR => Error::Interrupted,
-            Errno::EINVAL => Error::InvalidOperation,
-            Errno::ENOLCK => Error::OutOfMemory,
-            Errno::EWOULDBLOCK => Error::WouldBlock,
-            _ => Error::Other(e),
-        }
-    }
-}
-impl From<Error> for IOError {
-    fn from(e: Error) -> IOError {
-        use Error::*;
-        match e {
-            InvalidFd | InvalidOperation => IOError::new(IOErrorKind::InvalidInput, e),
-            Interrupted => IOError::new(IOErrorKind::Interrupted, e),
-            OutOfMemory | Other(_) => IOError::new(IOErrorKind::Other, e),
-            WouldBlock => IOError::new(IOErrorKind::WouldBlock, e),
-        }
-    }
-}
-
-pub struct FdLock<F: AsRawFd>(Option<F>);
-impl<F: AsRawFd> std::ops::Deref for FdLock<F> {
-    type Target = F;
-    fn deref(&self) -> &Self::Target {
-        self.0.as_ref().unwrap()
-    }
-}
-impl<F: AsRawFd> std::ops::DerefMut for FdLock<F> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        self.0.as_mut().unwrap()
-    }
-}
-impl<F: AsRawFd> FdLock<F> {
-    pub fn lock(f: F, lock_type: LockType, blocking: bool) -> Result<Self, Error> {
-        flock(
-            f.as_raw_fd(),
-            match lock_type {
-                LockType::Exclusive => {
-                    if blocking {
-                        FlockArg::LockExclusive
-                    } else {
-                        FlockArg::LockExclusiveNonblock
-                    }
-                }
-                LockType::Shared => {
-                    if blocking {
-                        FlockArg::LockShared
-                    } else {
-                        FlockArg::LockSharedNonblock
-                    }
-                }
-            },
-        )?;
-        Ok(FdLock(Some(f)))
-    }
-    pub fn map<Func: FnOnce(F) -> F_, F_: AsRawFd>(mut self, map_fn: Func) -> FdLock<F_> {
-        FdLock(self.0.take().map(map_fn))
-    }
-    pub fn unlock(mut self, blocking: bool) -> Result<F, (Self, Error)> {
-        match flock(
-            self.0.as_ref().unwrap().as_raw_fd(),
-            if blocking {
-                FlockArg::Unlock
-            } else {
-                FlockArg::UnlockNonblock
-            },
-        ) {
-            Ok(()) => Ok(self.0.take().unwrap()),
-            Err(e) => Err((self, e.into())),
-        }
-    }
-}
-impl<F: AsRawFd> std::ops::Drop for FdLock<F> {
-    fn drop(&mut self) {
-        if let Some(f) = self.0.take() {
-            flock(f.as_raw_fd(), FlockArg::Unlock).unwrap()
-        }
-    }
-}
+/// The handle types that can be locked: `AsFd` on unix, `AsHandle` on Windows. `OwnedFd`,
+/// `File` and friends all qualify; wrap handles that only expose a raw descriptor in
+/// [`RawFile`].
+pub use sys::AsFile;
+/// The raw handle traits, `AsRawFd` on unix and `AsRawHandle` on Windows.
+pub use sys::AsRawFile;
@@ -0,0 +1,562 @@
+use std::convert::TryFrom;
+use std::fs::File;
+use std::mem::ManuallyDrop;
+#[cfg(unix)]
+use std::os::raw::c_int;
+#[cfg(unix)]
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+#[cfg(target_os = "wasi")]
+use std::os::wasi::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+#[cfg(windows)]
+use std::os::windows::io::{AsHandle, AsRawHandle, BorrowedHandle, RawHandle};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::backend::LockBackend;
+use crate::sys::{self, AsFile};
+use crate::{order, Error, LockStyle, LockType, OnDropError, UnlockError};
+
+/// A held lock on `F`, released on drop.
+///
+/// The guard is `Send` and `Sync` whenever `F` and `B` are, and may be released on a different
+/// thread than the one that locked it: every backend's locks belong to the open file
+/// description or to the process, never to a thread. Nothing more is promised, so a guard over
+/// a handle that can't leave its thread can't either:
+///
+/// ```compile_fail,E0277
+/// fn assert_send<T: Send>() {}
+/// assert_send::<fd_lock_rs::FdLock<std::rc::Rc<std::fs::File>>>();
+/// ```
+///
+/// and neither can one whose backend is thread-bound:
+///
+/// ```compile_fail,E0277
+/// # use std::marker::PhantomData;
+/// # use fd_lock_rs::{backend::LockBackend, AsFile, Error, FdLock, LockType};
+/// struct ThreadBound(PhantomData<*const ()>);
+/// impl LockBackend for ThreadBound {
+///     // ...
+/// #     fn acquire<F: AsFile>(&self, _: &F, _: &LockType) -> Result<(), Error> { Ok(()) }
+/// #     fn try_acquire<F: AsFile>(&self, _: &F, _: &LockType) -> Result<(), Error> { Ok(()) }
+/// #     fn release<F: AsFile>(&self, _: &F) -> Result<(), Error> { Ok(()) }
+/// }
+/// fn assert_send<T: Send>() {}
+/// assert_send::<FdLock<std::fs::File, ThreadBound>>();
+/// ```
+pub struct FdLock<F: AsFile, B: LockBackend = LockStyle> {
+    // Only taken out by `into_locked_parts` and `drop`, which are the last uses of the guard.
+    f: ManuallyDrop<F>,
+    backend: ManuallyDrop<B>,
+    lock_type: LockType,
+    on_drop: ManuallyDrop<OnDropError>,
+    // Cleared when `unlocked` can't take the lock back, so nothing releases it again.
+    held: bool,
+}
+// Keep the guarantee above from regressing for the bundled backends.
+fn assert_send_sync<T: Send + Sync>() {}
+const _: fn() = assert_send_sync::<FdLock<File>>;
+const _: fn() = assert_send_sync::<FdLock<File, crate::backend::Auto>>;
+const _: fn() = assert_send_sync::<FdLock<File, crate::backend::DotLock>>;
+const _: fn() = assert_send_sync::<FdLock<File, crate::backend::Mandatory>>;
+const _: fn() = assert_send_sync::<FdLock<File, crate::backend::SmbCompat>>;
+const _: fn() = assert_send_sync::<FdLock<File, crate::backend::Registered>>;
+const _: fn() = assert_send_sync::<FdLock<File, crate::backend::Flock>>;
+const _: fn() = assert_send_sync::<FdLock<File, crate::backend::Fcntl>>;
+const _: fn() = assert_send_sync::<FdLock<File, crate::backend::Ofd>>;
+const _: fn() = assert_send_sync::<FdLockRef<'static, File>>;
+const _: fn() = assert_send_sync::<OwnedFdLock>;
+
+/// A lock on a file the guard borrows rather than owns.
+pub type FdLockRef<'a, F, B = LockStyle> = FdLock<&'a F, B>;
+/// A lock on a file the guard shares ownership of.
+pub type OwnedFdLock<F = File, B = LockStyle> = FdLock<Arc<F>, B>;
+
+impl<F: AsFile, B: LockBackend> std::ops::Deref for FdLock<F, B> {
+    type Target = F;
+    fn deref(&self) -> &Self::Target {
+        &self.f
+    }
+}
+impl<F: AsFile, B: LockBackend> std::ops::DerefMut for FdLock<F, B> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.f
+    }
+}
+macro_rules! try_from_handle {
+    ($($(#[$attr:meta])* $handle:ty),*) => {$(
+        $(#[$attr])*
+        /// Takes an exclusive lock without waiting, failing with [`Error::WouldBlock`] if the file
+        /// is already locked.
+        impl TryFrom<$handle> for FdLock<$handle> {
+            type Error = Error;
+            fn try_from(f: $handle) -> Result<Self, Error> {
+                FdLock::lock(f, LockType::Exclusive, false)
+            }
+        }
+    )*};
+}
+try_from_handle!(
+    File,
+    #[cfg(unix)]
+    std::os::unix::io::OwnedFd,
+    #[cfg(target_os = "wasi")]
+    std::os::wasi::io::OwnedFd,
+    #[cfg(windows)]
+    std::os::windows::io::OwnedHandle
+);
+
+#[cfg(any(unix, target_os = "wasi"))]
+impl<F: AsFile, B: LockBackend> AsFd for FdLock<F, B> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.f.as_fd()
+    }
+}
+#[cfg(any(unix, target_os = "wasi"))]
+impl<F: AsFile, B: LockBackend> AsRawFd for FdLock<F, B> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.f.as_fd().as_raw_fd()
+    }
+}
+#[cfg(windows)]
+impl<F: AsFile, B: LockBackend> AsHandle for FdLock<F, B> {
+    fn as_handle(&self) -> BorrowedHandle<'_> {
+        self.f.as_handle()
+    }
+}
+#[cfg(windows)]
+impl<F: AsFile, B: LockBackend> AsRawHandle for FdLock<F, B> {
+    fn as_raw_handle(&self) -> RawHandle {
+        self.f.as_handle().as_raw_handle()
+    }
+}
+// Only the raw handle is shown so `F` needn't be Debug. `held` is false once `unlocked` has
+// failed to take the lock back.
+impl<F: AsFile, B: LockBackend> std::fmt::Debug for FdLock<F, B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("FdLock")
+            .field("raw", &sys::as_raw(&*self.f))
+            .field("lock_type", &self.lock_type)
+            .field("held", &self.held)
+            .finish()
+    }
+}
+impl<F: AsFile> FdLock<F> {
+    /// Locks `f` with the default [`LockStyle`].
+    pub fn lock(f: F, lock_type: LockType, blocking: bool) -> Result<Self, Error> {
+        Self::lock_with(f, LockStyle::default(), lock_type, blocking)
+    }
+    /// Tries to lock `f` with the default [`LockStyle`] without waiting, returning `None` if
+    /// someone else holds it.
+    pub fn try_lock(f: F, lock_type: LockType) -> Result<Option<Self>, Error> {
+        Self::try_lock_with(f, LockStyle::default(), lock_type)
+    }
+    /// Wraps a handle that is already locked, e.g. one inherited from a parent process, with
+    /// the default [`LockStyle`] and without locking it again.
+    ///
+    /// # Safety
+    ///
+    /// See [`adopt_with`](FdLock::adopt_with).
+    pub unsafe fn adopt(f: F, lock_type: LockType) -> Self {
+        Self::adopt_with(f, LockStyle::default(), lock_type)
+    }
+}
+impl<F: AsFile + sys::FromRawFile> FdLock<F> {
+    /// Rebuilds a guard given up with [`into_raw_parts`](FdLock::into_raw_parts), for a lock
+    /// taken with the default [`LockStyle`].
+    ///
+    /// # Safety
+    ///
+    /// See [`from_raw_parts_with`](FdLock::from_raw_parts_with).
+    pub unsafe fn from_raw_parts(raw: sys::RawDescriptor, lock_type: LockType) -> Self {
+        Self::from_raw_parts_with(raw, LockStyle::default(), lock_type)
+    }
+}
+impl<'a, F: AsFile> FdLockRef<'a, F> {
+    /// Locks a file without taking ownership of it; the file stays borrowed until the guard is
+    /// released.
+    pub fn lock_ref(f: &'a F, lock_type: LockType, blocking: bool) -> Result<Self, Error> {
+        Self::lock(f, lock_type, blocking)
+    }
+}
+#[cfg(unix)]
+impl<F: AsFile> FdLock<F> {
+    /// Locks `f` by passing `operation` (`LOCK_EX`, `LOCK_SH`, `LOCK_NB` and any other flags)
+    /// to flock(2) unchanged, for flags the typed API doesn't cover. The guard holds a
+    /// [`LockStyle::Flock`] lock, shared if `operation` includes `LOCK_SH`.
+    pub fn lock_raw(f: F, operation: c_int) -> Result<Self, Error> {
+        let lock_type = sys::flock_raw(&f, operation)?;
+        Ok(FdLock::assume_locked(f, LockStyle::Flock, lock_type))
+    }
+    /// Like [`relock`](Self::relock), with a raw flock(2) operation as for
+    /// [`lock_raw`](Self::lock_raw). Fails with [`Error::InvalidOperation`] unless the guard
+    /// holds a [`LockStyle::Flock`] lock. Unlike `relock`, a failed conversion isn't undone, and
+    /// Linux may have dropped the old lock by then.
+    pub fn relock_raw(&mut self, operation: c_int) -> Result<(), Error> {
+        if !self.held || *self.backend != LockStyle::Flock {
+            return Err(Error::InvalidOperation);
+        }
+        self.lock_type = sys::flock_raw(&*self.f, operation)?;
+        Ok(())
+    }
+}
+impl<F: AsFile> OwnedFdLock<F> {
+    /// Locks a file shared through an [`Arc`], giving a `'static` guard for async tasks while
+    /// other clones of the `Arc` stay usable. The lock belongs to the file, not the clone, so
+    /// it's released when the guard is dropped even if other clones remain.
+    pub fn lock_owned(f: Arc<F>, lock_type: LockType, blocking: bool) -> Result<Self, Error> {
+        Self::lock(f, lock_type, blocking)
+    }
+    pub fn try_lock_owned(f: Arc<F>, lock_type: LockType) -> Result<Option<Self>, Error> {
+        Self::try_lock(f, lock_type)
+    }
+}
+impl<F: AsFile, B: LockBackend> FdLock<F, B> {
+    /// Like [`FdLock::lock`], but with an explicit backend: a [`LockStyle`] chosen at runtime,
+    /// one of the types in [`backend`](crate::backend), or a custom [`LockBackend`]. Styles the
+    /// platform (or the selected syscall backend) can't provide fail with [`Error::Unsupported`].
+    pub fn lock_with(f: F, backend: B, lock_type: LockType, blocking: bool) -> Result<Self, Error> {
+        if blocking {
+            backend.acquire(&f, &lock_type)?;
+        } else {
+            backend.try_acquire(&f, &lock_type)?;
+        }
+        Ok(FdLock::assume_locked(f, backend, lock_type))
+    }
+    /// Like [`FdLock::try_lock`], with an explicit backend.
+    pub fn try_lock_with(f: F, backend: B, lock_type: LockType) -> Result<Option<Self>, Error> {
+        match Self::lock_with(f, backend, lock_type, false) {
+            Ok(lock) => Ok(Some(lock)),
+            Err(Error::WouldBlock) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    // Wraps a handle that `backend` has already locked.
+    pub(crate) fn assume_locked(f: F, backend: B, lock_type: LockType) -> Self {
+        let lock = FdLock::wrap(f, backend, lock_type);
+        order::acquired(&*lock.f);
+        lock
+    }
+    // Like `assume_locked`, for a lock the lock order check already knows about.
+    fn wrap(f: F, backend: B, lock_type: LockType) -> Self {
+        FdLock {
+            f: ManuallyDrop::new(f),
+            backend: ManuallyDrop::new(backend),
+            lock_type,
+            on_drop: ManuallyDrop::new(OnDropError::default()),
+            held: true,
+        }
+    }
+    // Takes the handle and backend out without releasing the lock.
+    pub(crate) fn into_locked_parts(self) -> (F, B) {
+        let (f, backend, _) = self.into_parts();
+        (f, backend)
+    }
+    fn into_parts(self) -> (F, B, OnDropError) {
+        order::released(&*self.f);
+        let mut this = ManuallyDrop::new(self);
+        unsafe {
+            (
+                ManuallyDrop::take(&mut this.f),
+                ManuallyDrop::take(&mut this.backend),
+                ManuallyDrop::take(&mut this.on_drop),
+            )
+        }
+    }
+    /// Sets what dropping the guard does if the release fails. Defaults to
+    /// [`OnDropError::Ignore`].
+    pub fn set_on_drop_error(&mut self, on_drop: OnDropError) {
+        *self.on_drop = on_drop;
+    }
+    /// Returns the inner handle without releasing the lock, which then stays held until the file
+    /// is closed, usually at process exit. Meant for pidfiles and similar. Process-wide fcntl(2)
+    /// locks are also dropped when any other descriptor for the file is closed, and a
+    /// [`DotLock`] leaks its lock file outright.
+    ///
+    /// [`DotLock`]: crate::backend::DotLock
+    pub fn leak(self) -> F {
+        self.into_locked_parts().0
+    }
+    /// Gives up the guard without releasing the lock, returning the raw descriptor and what it
+    /// holds. Rebuild the guard with [`from_raw_parts`](FdLock::from_raw_parts), for example
+    /// in a program started with exec(2). The descriptor must not be close-on-exec for that;
+    /// std opens files with `O_CLOEXEC`, so clear it first.
+    pub fn into_raw_parts(self) -> (sys::RawDescriptor, LockType)
+    where
+        F: sys::IntoRawFile,
+    {
+        let lock_type = self.lock_type;
+        (sys::into_raw(self.leak()), lock_type)
+    }
+    /// Like [`adopt`](FdLock::adopt), with an explicit backend.
+    ///
+    /// # Safety
+    ///
+    /// `f` must already hold a `lock_type` lock taken by `backend`, which nothing else will
+    /// release. Otherwise releasing the guard can drop someone else's lock.
+    pub unsafe fn adopt_with(f: F, backend: B, lock_type: LockType) -> Self {
+        FdLock::assume_locked(f, backend, lock_type)
+    }
+    /// Rebuilds a guard given up with [`into_raw_parts`](FdLock::into_raw_parts).
+    ///
+    /// # Safety
+    ///
+    /// `raw` must be an open handle that nothing else owns, already locked as `lock_type` by
+    /// `backend`.
+    pub unsafe fn from_raw_parts_with(
+        raw: sys::RawDescriptor,
+        backend: B,
+        lock_type: LockType,
+    ) -> Self
+    where
+        F: sys::FromRawFile,
+    {
+        FdLock::adopt_with(sys::from_raw(raw), backend, lock_type)
+    }
+    pub fn get_ref(&self) -> &F {
+        &self.f
+    }
+    pub fn get_mut(&mut self) -> &mut F {
+        &mut self.f
+    }
+    /// Releases the lock and returns the inner handle, ignoring a failed release. Use
+    /// [`unlock`](Self::unlock) to get the error and the guard back instead.
+    pub fn into_inner(self) -> F {
+        let held = self.held;
+        let (f, backend) = self.into_locked_parts();
+        if held {
+            let _ = backend.release(&f);
+        }
+        f
+    }
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+    /// The kind of lock currently held.
+    pub fn lock_type(&self) -> LockType {
+        self.lock_type
+    }
+    pub fn is_exclusive(&self) -> bool {
+        self.lock_type == LockType::Exclusive
+    }
+    pub fn is_shared(&self) -> bool {
+        self.lock_type == LockType::Shared
+    }
+    /// Swaps the handle for another one, such as a wrapper around it. Fails with
+    /// [`Error::InvalidOperation`] if the new handle refers to a different file (by device and
+    /// inode, or volume and file index on Windows; WASI can't tell). The lock stays with the
+    /// original file in that case, and the new handle is dropped without being unlocked.
+    pub fn map<Func: FnOnce(F) -> F_, F_: AsFile>(
+        self,
+        map_fn: Func,
+    ) -> Result<FdLock<F_, B>, Error> {
+        self.try_map(|f| Ok(map_fn(f)))
+    }
+    /// Like [`map`](Self::map), for a closure that can fail. A handle the closure consumed
+    /// before failing keeps whatever lock it had.
+    pub fn try_map<Func, F_>(self, map_fn: Func) -> Result<FdLock<F_, B>, Error>
+    where
+        Func: FnOnce(F) -> Result<F_, Error>,
+        F_: AsFile,
+    {
+        let id = match sys::file_id(self.get_ref()) {
+            Ok(id) => Some(id),
+            Err(Error::Unsupported) => None,
+            Err(e) => return Err(e),
+        };
+        let lock_type = self.lock_type;
+        let (f, backend, on_drop) = self.into_parts();
+        let f = map_fn(f)?;
+        if let Some(id) = id {
+            if sys::file_id(&f)? != id {
+                return Err(Error::InvalidOperation);
+            }
+        }
+        let mut lock = FdLock::assume_locked(f, backend, lock_type);
+        lock.set_on_drop_error(on_drop);
+        Ok(lock)
+    }
+    /// Sets whether the descriptor is closed on exec(2). Clearing it lets a program started
+    /// with exec keep holding the lock (see [`into_raw_parts`](Self::into_raw_parts)); setting
+    /// it makes sure children never inherit it. On Windows this controls handle inheritance.
+    pub fn set_cloexec(&self, cloexec: bool) -> Result<(), Error> {
+        sys::set_cloexec(&*self.f, cloexec)
+    }
+    /// Checks that the lock hasn't been lost behind the guard's back, e.g. by another thread
+    /// closing a descriptor for the file while holding a process-wide fcntl(2) lock. Returns
+    /// `false` if it's gone.
+    ///
+    /// Nothing is locked or probed: the answer comes from the kernel's lock tables in `/proc`,
+    /// so it's best-effort. [`LockStyle::Flock`] and [`LockStyle::Ofd`] locks are looked for on
+    /// the guard's own open file description; [`LockStyle::Fcntl`] ones count if this process
+    /// holds any on the file. Only implemented on Linux and Android with `/proc` mounted;
+    /// elsewhere, and for backends that can't tell, fails with [`Error::Unsupported`].
+    pub fn verify_still_held(&self) -> Result<bool, Error> {
+        self.backend.is_held(&*self.f)
+    }
+    /// Converts an exclusive lock to a shared one without releasing it in between, so no writer
+    /// can get in first. A shared lock is returned as is.
+    ///
+    /// Atomic with the record lock styles. flock(2) converts by releasing the lock and taking a
+    /// new one, so with [`LockStyle::Flock`] this fails with [`Error::Unsupported`], as it does
+    /// for other backends that can't convert.
+    pub fn downgrade(self) -> Result<Self, (Self, Error)> {
+        self.convert(LockType::Shared, true)
+    }
+    /// Converts a shared lock to an exclusive one, waiting for other readers to go away. An
+    /// exclusive lock is returned as is.
+    ///
+    /// Two processes that both hold a shared lock and both call this wait for each other forever.
+    /// Process-wide fcntl(2) locks detect this and fail one side with [`Error::Deadlock`];
+    /// flock(2) and OFD locks don't. Prefer [`try_upgrade`](Self::try_upgrade), or take an
+    /// exclusive lock to begin with, when more than one process may upgrade. Not supported on
+    /// Windows.
+    pub fn upgrade(self) -> Result<Self, (Self, Error)> {
+        self.convert(LockType::Exclusive, true)
+    }
+    /// Like [`upgrade`](Self::upgrade), but fails with [`Error::WouldBlock`] while other
+    /// readers hold the lock. The shared lock is kept on failure, except with
+    /// [`LockStyle::Flock`]: flock(2) has already let go of it, and if taking it back would
+    /// wait, the lock is lost and the error from that attempt is returned.
+    pub fn try_upgrade(self) -> Result<Self, (Self, Error)> {
+        self.convert(LockType::Exclusive, false)
+    }
+    /// Switches the held lock to `lock_type` in place, without the gap an unlock followed by a
+    /// new lock would leave. Going to [`LockType::Exclusive`] has the same caveats as
+    /// [`try_upgrade`](Self::try_upgrade) and [`upgrade`](Self::upgrade), and going to
+    /// [`LockType::Shared`] those of [`downgrade`](Self::downgrade). Otherwise the guard keeps
+    /// its old lock on failure.
+    pub fn relock(&mut self, lock_type: LockType, blocking: bool) -> Result<(), Error> {
+        if !self.held {
+            return Err(Error::InvalidOperation);
+        }
+        if self.lock_type == lock_type {
+            return Ok(());
+        }
+        self.backend
+            .convert(&*self.f, &self.lock_type, &lock_type, blocking)?;
+        self.lock_type = lock_type;
+        Ok(())
+    }
+    /// Releases the lock, runs `func` on the handle, then waits to take the lock back with the
+    /// same type. If releasing fails `func` is not run. If taking the lock back fails (or `func`
+    /// panics) the guard is left not holding the lock: dropping or unlocking it releases
+    /// nothing, and [`relock`](Self::relock) and `unlocked` fail with
+    /// [`Error::InvalidOperation`].
+    pub fn unlocked<T, Func: FnOnce(&mut F) -> T>(&mut self, func: Func) -> Result<T, Error> {
+        if !self.held {
+            return Err(Error::InvalidOperation);
+        }
+        self.backend.release(&*self.f)?;
+        self.held = false;
+        let res = func(&mut self.f);
+        self.backend.acquire(&*self.f, &self.lock_type)?;
+        self.held = true;
+        Ok(res)
+    }
+    fn convert(mut self, to: LockType, blocking: bool) -> Result<Self, (Self, Error)> {
+        match self.relock(to, blocking) {
+            Ok(()) => Ok(self),
+            Err(e) => Err((self, e)),
+        }
+    }
+    /// Releases the lock and returns the inner handle. On failure the error carries the guard,
+    /// which still holds the lock. Releasing never waits for other lockers, though on network
+    /// filesystems the call itself can block; async code can use `unlock_async` (with one of the
+    /// runtime features) instead.
+    pub fn unlock(self) -> Result<F, UnlockError<F, B>> {
+        if !self.held {
+            return Ok(self.into_locked_parts().0);
+        }
+        match self.backend.release(&*self.f) {
+            Ok(()) => Ok(self.into_locked_parts().0),
+            Err(e) => Err(UnlockError::new(self, e)),
+        }
+    }
+    /// Like [`unlock`](Self::unlock), but hands back only the guard on failure, for callers that
+    /// will retry or leave the release to drop.
+    pub fn try_unlock(self) -> Result<F, Self> {
+        self.unlock().map_err(UnlockError::into_lock)
+    }
+    /// The old form of [`unlock`](Self::unlock). `blocking` never had an effect.
+    #[deprecated(note = "use `unlock`, which takes no flag")]
+    pub fn unlock_blocking(self, _blocking: bool) -> Result<F, (Self, Error)> {
+        self.unlock().map_err(UnlockError::into_parts)
+    }
+}
+impl FdLock<File> {
+    /// Opens `path` for reading and writing (creating it if needed) and locks it. On macOS and
+    /// the BSDs the lock is taken atomically by `open(2)` itself (`O_EXLOCK`/`O_SHLOCK`), so
+    /// there is no window where the file is open but unlocked; elsewhere this is an open
+    /// followed by [`FdLock::lock`].
+    pub fn open_locked<P: AsRef<Path>>(
+        path: P,
+        lock_type: LockType,
+        blocking: bool,
+    ) -> Result<Self, Error> {
+        let style = LockStyle::default();
+        let f = sys::open_locked(path.as_ref(), &style, &lock_type, blocking)?;
+        Ok(FdLock::assume_locked(f, style, lock_type))
+    }
+    /// Like [`FdLock::open_locked`], but resolves a relative `path` against the directory
+    /// `dir` (with `openat(2)`), for callers that only hold a descriptor for the directory.
+    #[cfg(unix)]
+    pub fn open_locked_at<D: AsFile, P: AsRef<Path>>(
+        dir: &D,
+        path: P,
+        lock_type: LockType,
+        blocking: bool,
+    ) -> Result<Self, Error> {
+        let style = LockStyle::default();
+        let f = sys::open_locked_at(
+            sys::as_raw(dir),
+            path.as_ref(),
+            &style,
+            &lock_type,
+            blocking,
+        )?;
+        Ok(FdLock::assume_locked(f, style, lock_type))
+    }
+}
+impl FdLock<File> {
+    /// Duplicates the descriptor into a second guard for the same lock, e.g. to hand one to a
+    /// writer thread and one to a reader thread.
+    ///
+    /// A duplicate shares the open file description, and with it the lock, so whichever guard
+    /// is released or dropped first releases the lock for both; releasing the second one is
+    /// then a no-op. A [`SharedFdLock`](crate::SharedFdLock) releases on the last drop instead.
+    /// Only [`LockStyle::Flock`] and [`LockStyle::Ofd`] locks belong to the open file
+    /// description; [`LockStyle::Fcntl`] fails with [`Error::Unsupported`], as do the other
+    /// backends by not offering this at all.
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        if *self.backend == LockStyle::Fcntl {
+            return Err(Error::Unsupported);
+        }
+        if !self.held {
+            return Err(Error::InvalidOperation);
+        }
+        let f = self.f.try_clone().map_err(sys::io_error)?;
+        // Still one lock to the lock order check, gone at the first release since that's the
+        // one that counts.
+        let mut lock = FdLock::wrap(f, *self.backend, self.lock_type);
+        lock.set_on_drop_error((*self.on_drop).clone());
+        Ok(lock)
+    }
+}
+impl<F: AsFile, B: LockBackend> std::ops::Drop for FdLock<F, B> {
+    fn drop(&mut self) {
+        order::released(&*self.f);
+        let res = if self.held {
+            self.backend.release(&*self.f)
+        } else {
+            Ok(())
+        };
+        let on_drop = unsafe {
+            ManuallyDrop::drop(&mut self.f);
+            ManuallyDrop::drop(&mut self.backend);
+            ManuallyDrop::take(&mut self.on_drop)
+        };
+        if let Err(e) = res {
+            on_drop.handle(e)
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind as IOErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{sys, AsFile, Error, FdLock, LockType};
+
+/// A pidfile: a file that is created if needed, locked exclusively, and holds the holder's PID.
+/// The file is removed again when this is dropped (or [`remove`](Lockfile::remove)d).
+///
+/// Removal is done while the lock is still held, and taking the lock checks afterwards that the
+/// path still names the locked file, retrying if not. That way a process that opened the file
+/// just before it was removed can't end up holding a lock on a file nobody else can see.
+#[derive(Debug)]
+pub struct Lockfile {
+    path: PathBuf,
+    lock: Option<FdLock<File>>,
+}
+impl Lockfile {
+    /// Takes the lock at `path` without waiting, failing with [`Error::WouldBlock`] if another
+    /// process holds it.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::lock(path, false)
+    }
+    /// Takes the lock at `path`, waiting for it if `blocking`, and writes our PID to it.
+    pub fn lock<P: AsRef<Path>>(path: P, blocking: bool) -> Result<Self, Error> {
+        let path = path.as_ref();
+        Self::hold(path, lock_path(path, LockType::Exclusive, blocking)?)
+    }
+    /// Takes over the lock at `path` from a holder that has exited, failing with
+    /// [`Error::WouldBlock`] unless the recorded PID is one [`HolderStatus::Dead`] would
+    /// report. If the dead holder's lock was inherited by a process that is still running, the
+    /// file is removed and a new one locked in its place.
+    ///
+    /// Breakers take turns through an exclusive lock on `<path>.break`, which is left behind.
+    /// Everything is checked again against the open file, so a holder that replaced the file in
+    /// the meantime is never broken. What remains is an inheritor letting go of the old file
+    /// just as it is removed, which a concurrent [`Lockfile::lock`] could pick up.
+    pub fn force_break<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let mut break_path = OsString::from(path);
+        break_path.push(".break");
+        let break_file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(break_path)
+            .map_err(sys::io_error)?;
+        let _breaking = FdLock::lock(break_file, LockType::Exclusive, true)?;
+
+        let mut f = match OpenOptions::new().read(true).write(true).open(path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == IOErrorKind::NotFound => return Self::create(path),
+            Err(e) => return Err(sys::io_error(e)),
+        };
+        let mut contents = String::new();
+        f.read_to_string(&mut contents).map_err(sys::io_error)?;
+        match status(&contents)? {
+            HolderStatus::Dead(_) => (),
+            _ => return Err(Error::WouldBlock),
+        }
+        let id = sys::file_id(&f)?;
+        match FdLock::try_lock(f, LockType::Exclusive)? {
+            // Nobody holds it after all, so take it over in place.
+            Some(lock) if names(path, &lock)? => Self::hold(path, lock),
+            Some(_) => Self::create(path),
+            None => {
+                // Whoever holds it can't be a `Lockfile` that locked it properly, or the PID
+                // would be theirs; nobody else can lock it either, so it can't be replaced
+                // under us between this check and the removal. (Other breakers are waiting.)
+                if sys::path_id(path)? == Some(id) {
+                    std::fs::remove_file(path).map_err(sys::io_error)?;
+                }
+                Self::create(path)
+            }
+        }
+    }
+    fn hold(path: &Path, mut lock: FdLock<File>) -> Result<Self, Error> {
+        lock.set_len(0).map_err(sys::io_error)?;
+        // force_break has read up to the old end already
+        lock.seek(SeekFrom::Start(0)).map_err(sys::io_error)?;
+        writeln!(lock, "{}", std::process::id()).map_err(sys::io_error)?;
+        Ok(Lockfile {
+            path: path.to_owned(),
+            lock: Some(lock),
+        })
+    }
+    /// Reads the PID recorded at `path` and checks whether that process is still running, e.g.
+    /// to decide between waiting for the lock and treating it as abandoned. PIDs are reused, so
+    /// [`HolderStatus::Alive`] can name an unrelated process; whether the lock is held is what
+    /// actually matters.
+    pub fn holder_alive<P: AsRef<Path>>(path: P) -> Result<HolderStatus, Error> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => status(&contents),
+            Err(e) if e.kind() == IOErrorKind::NotFound => Ok(HolderStatus::Missing),
+            Err(e) => Err(sys::io_error(e)),
+        }
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    pub fn file(&self) -> &File {
+        self.lock.as_ref().unwrap()
+    }
+    /// Removes the file and releases the lock, reporting any error that drop would ignore.
+    pub fn remove(mut self) -> Result<(), Error> {
+        self.cleanup()
+    }
+    fn cleanup(&mut self) -> Result<(), Error> {
+        let lock = match self.lock.take() {
+            Some(lock) => lock,
+            None => return Ok(()),
+        };
+        if names(&self.path, &lock)? {
+            std::fs::remove_file(&self.path).map_err(sys::io_error)?;
+        }
+        lock.unlock()?;
+        Ok(())
+    }
+}
+impl Drop for Lockfile {
+    fn drop(&mut self) {
+        let _ = self.cleanup();
+    }
+}
+
+/// What [`Lockfile::holder_alive`] found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HolderStatus {
+    /// The recorded process is running.
+    Alive(u32),
+    /// The recorded process has exited.
+    Dead(u32),
+    /// The file doesn't exist.
+    Missing,
+    /// The file has no valid PID in it, which is also what it looks like while a new holder is
+    /// between taking the lock and writing its PID.
+    NoPid,
+}
+
+fn status(contents: &str) -> Result<HolderStatus, Error> {
+    // 0 and anything past i32::MAX would name process groups, not a process.
+    let pid = match contents.trim().parse::<u32>() {
+        Ok(pid) if pid != 0 && pid <= i32::MAX as u32 => pid,
+        _ => return Ok(HolderStatus::NoPid),
+    };
+    Ok(if sys::process_alive(pid)? {
+        HolderStatus::Alive(pid)
+    } else {
+        HolderStatus::Dead(pid)
+    })
+}
+
+/// Opens `path` (creating it if needed) and locks it, until the file locked is the one `path`
+/// names once the lock is held. Anything that removes or replaces the file while holding its
+/// lock, as [`Lockfile`] and the atomic writers do, relies on this.
+pub(crate) fn lock_path(
+    path: &Path,
+    lock_type: LockType,
+    blocking: bool,
+) -> Result<FdLock<File>, Error> {
+    loop {
+        let f = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            // what the holder wrote has to survive until we have the lock
+            .truncate(false)
+            .open(path)
+            .map_err(sys::io_error)?;
+        let lock = FdLock::lock(f, lock_type, blocking)?;
+        if names(path, &lock)? {
+            return Ok(lock);
+        }
+    }
+}
+
+/// Whether `path` still names the file `f`. Goes by the path alone: opening the file again
+/// and closing it would release an fcntl(2) lock on it.
+fn names<F: AsFile>(path: &Path, f: &F) -> Result<bool, Error> {
+    Ok(sys::path_id(path)? == Some(sys::file_id(f)?))
+}
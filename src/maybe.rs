@@ -0,0 +1,102 @@
+use crate::backend::LockBackend;
+use crate::{AsFile, Error, FdLock, LockStyle, LockType};
+
+/// A handle that may or may not be locked at the moment, for keeping one type around while
+/// deciding at runtime when to lock.
+pub struct MaybeLocked<F: AsFile, B: LockBackend = LockStyle>(State<F, B>);
+
+enum State<F: AsFile, B: LockBackend> {
+    Unlocked(F, B),
+    Locked(FdLock<F, B>),
+}
+
+impl<F: AsFile> MaybeLocked<F> {
+    /// Starts out unlocked, to be locked with the default [`LockStyle`].
+    pub fn new(f: F) -> Self {
+        Self::with_backend(f, LockStyle::default())
+    }
+}
+impl<F: AsFile, B: LockBackend> MaybeLocked<F, B> {
+    pub fn with_backend(f: F, backend: B) -> Self {
+        MaybeLocked(State::Unlocked(f, backend))
+    }
+    /// Takes the lock, or converts it with [`FdLock::relock`] if one is already held.
+    pub fn lock(self, lock_type: LockType, blocking: bool) -> Result<Self, (Self, Error)> {
+        match self.0 {
+            State::Unlocked(f, backend) => {
+                let res = if blocking {
+                    backend.acquire(&f, &lock_type)
+                } else {
+                    backend.try_acquire(&f, &lock_type)
+                };
+                match res {
+                    Ok(()) => Ok(FdLock::assume_locked(f, backend, lock_type).into()),
+                    Err(e) => Err((MaybeLocked(State::Unlocked(f, backend)), e)),
+                }
+            }
+            State::Locked(mut lock) => match lock.relock(lock_type, blocking) {
+                Ok(()) => Ok(lock.into()),
+                Err(e) => Err((lock.into(), e)),
+            },
+        }
+    }
+    /// Releases the lock, if held.
+    pub fn unlock(self) -> Result<Self, (Self, Error)> {
+        match self.0 {
+            State::Unlocked(..) => Ok(self),
+            State::Locked(lock) => match lock.backend().release(lock.get_ref()) {
+                Ok(()) => {
+                    let (f, backend) = lock.into_locked_parts();
+                    Ok(MaybeLocked(State::Unlocked(f, backend)))
+                }
+                Err(e) => Err((lock.into(), e)),
+            },
+        }
+    }
+    pub fn is_locked(&self) -> bool {
+        matches!(self.0, State::Locked(_))
+    }
+    /// The kind of lock held, if any.
+    pub fn lock_type(&self) -> Option<LockType> {
+        match &self.0 {
+            State::Unlocked(..) => None,
+            State::Locked(lock) => Some(lock.lock_type()),
+        }
+    }
+    /// The guard, if locked.
+    pub fn as_locked(&self) -> Option<&FdLock<F, B>> {
+        match &self.0 {
+            State::Unlocked(..) => None,
+            State::Locked(lock) => Some(lock),
+        }
+    }
+    /// Returns the handle, releasing the lock first if held, as [`FdLock::into_inner`] does.
+    pub fn into_inner(self) -> F {
+        match self.0 {
+            State::Unlocked(f, _) => f,
+            State::Locked(lock) => lock.into_inner(),
+        }
+    }
+}
+impl<F: AsFile, B: LockBackend> From<FdLock<F, B>> for MaybeLocked<F, B> {
+    fn from(lock: FdLock<F, B>) -> Self {
+        MaybeLocked(State::Locked(lock))
+    }
+}
+impl<F: AsFile, B: LockBackend> std::ops::Deref for MaybeLocked<F, B> {
+    type Target = F;
+    fn deref(&self) -> &Self::Target {
+        match &self.0 {
+            State::Unlocked(f, _) => f,
+            State::Locked(lock) => lock,
+        }
+    }
+}
+impl<F: AsFile, B: LockBackend> std::ops::DerefMut for MaybeLocked<F, B> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match &mut self.0 {
+            State::Unlocked(f, _) => f,
+            State::Locked(lock) => lock,
+        }
+    }
+}
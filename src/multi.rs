@@ -0,0 +1,115 @@
+use crate::backend::LockBackend;
+use crate::sys::{self, AsFile};
+use crate::{Error, FdLock, LockFuture, LockStyle, LockType};
+
+/// Locks every file in `files` with the default [`LockStyle`], or none of them. See
+/// [`lock_all_with`].
+pub fn lock_all<F: AsFile>(
+    files: Vec<F>,
+    lock_type: LockType,
+    blocking: bool,
+) -> Result<LockSet<F>, Error> {
+    lock_all_with(files, LockStyle::default(), lock_type, blocking)
+}
+
+/// Locks every file in `files`, or none of them, returning a [`LockSet`] with the guards in
+/// the same order as `files`.
+///
+/// Files are locked one at a time in order of their (device, inode), so two callers locking
+/// overlapping sets of files this way never deadlock against each other, even when waiting. If
+/// any lock fails (including with [`Error::WouldBlock`] when not `blocking`), the ones already
+/// taken are released before the error is returned. Listing the same file twice fails with
+/// [`Error::InvalidOperation`], since the second lock would wait on the first forever.
+pub fn lock_all_with<F: AsFile, B: LockBackend + Clone>(
+    files: Vec<F>,
+    backend: B,
+    lock_type: LockType,
+    blocking: bool,
+) -> Result<LockSet<F, B>, Error> {
+    let mut locks = Vec::with_capacity(files.len());
+    for (i, f) in in_lock_order(files)? {
+        // Any locks already in `locks` are released when it's dropped.
+        let lock = FdLock::lock_with(f, backend.clone(), lock_type, blocking)?;
+        locks.push((i, lock));
+    }
+    Ok(LockSet(in_given_order(locks)))
+}
+
+/// Locks every file in `files` with the default [`LockStyle`], or none of them. See
+/// [`lock_all_async_with`].
+pub async fn lock_all_async<F: AsFile>(
+    files: Vec<F>,
+    lock_type: LockType,
+) -> Result<Vec<FdLock<F>>, Error> {
+    lock_all_async_with(files, LockStyle::default(), lock_type).await
+}
+
+/// Locks every file in `files`, or none of them, returning the guards in the same order as
+/// `files`.
+///
+/// Files are locked one at a time in order of their (device, inode), with [`LockFuture`]'s
+/// try-then-backoff, so two callers locking overlapping sets of files this way never deadlock
+/// against each other. If any lock fails, the ones already taken are released before the error
+/// is returned. Listing the same file twice fails with [`Error::InvalidOperation`], since the
+/// second lock would wait on the first forever.
+pub async fn lock_all_async_with<F: AsFile, B: LockBackend + Clone>(
+    files: Vec<F>,
+    backend: B,
+    lock_type: LockType,
+) -> Result<Vec<FdLock<F, B>>, Error> {
+    let mut locks = Vec::with_capacity(files.len());
+    for (i, f) in in_lock_order(files)? {
+        // Any locks already in `locks` are released when it's dropped.
+        let lock = LockFuture::with_backend(f, backend.clone(), lock_type).await?;
+        locks.push((i, lock));
+    }
+    Ok(in_given_order(locks))
+}
+
+/// Pairs each file with its index in `files`, sorted by (device, inode).
+fn in_lock_order<F: AsFile>(files: Vec<F>) -> Result<Vec<(usize, F)>, Error> {
+    let mut files = files
+        .into_iter()
+        .enumerate()
+        .map(|(i, f)| Ok((sys::file_id(&f)?, i, f)))
+        .collect::<Result<Vec<_>, Error>>()?;
+    files.sort_by_key(|(id, i, _)| (*id, *i));
+    if files.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+        return Err(Error::InvalidOperation);
+    }
+    Ok(files.into_iter().map(|(_, i, f)| (i, f)).collect())
+}
+
+fn in_given_order<T>(mut locks: Vec<(usize, T)>) -> Vec<T> {
+    locks.sort_by_key(|(i, _)| *i);
+    locks.into_iter().map(|(_, lock)| lock).collect()
+}
+
+/// The guards from [`lock_all`], released together when this is dropped.
+#[derive(Debug)]
+pub struct LockSet<F: AsFile, B: LockBackend = LockStyle>(Vec<FdLock<F, B>>);
+
+impl<F: AsFile, B: LockBackend> LockSet<F, B> {
+    /// Releases every lock, returning the handles in the original order. Stops at the first
+    /// failure, leaving the rest to be released on drop.
+    pub fn unlock(self) -> Result<Vec<F>, Error> {
+        self.0
+            .into_iter()
+            .map(|lock| lock.unlock().map_err(Error::from))
+            .collect()
+    }
+    pub fn into_inner(self) -> Vec<FdLock<F, B>> {
+        self.0
+    }
+}
+impl<F: AsFile, B: LockBackend> std::ops::Deref for LockSet<F, B> {
+    type Target = [FdLock<F, B>];
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl<F: AsFile, B: LockBackend> std::ops::DerefMut for LockSet<F, B> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
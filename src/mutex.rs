@@ -0,0 +1,159 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::lockfile::lock_path;
+use crate::{atomic, sys, Error, FdLock, LockType};
+
+/// A value kept as JSON in a file, that processes take turns to change:
+/// [`lock`](FileMutex::lock) takes an exclusive lock on the file and reads the value, and the
+/// guard writes it back (if it was changed) before releasing the lock.
+///
+/// The write replaces the file rather than overwriting it, so a crash never leaves it half
+/// written. Everything touching the file should go through a `FileMutex`.
+pub struct FileMutex<T> {
+    path: PathBuf,
+    value: PhantomData<fn() -> T>,
+}
+impl<T: Serialize + DeserializeOwned> FileMutex<T> {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        FileMutex {
+            path: path.as_ref().to_owned(),
+            value: PhantomData,
+        }
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// Waits for the lock and reads the value, starting from `T::default()` if the file is
+    /// missing or empty.
+    pub fn lock(&self) -> Result<FileMutexGuard<T>, FileMutexError>
+    where
+        T: Default,
+    {
+        self.lock_or_else(T::default)
+    }
+    /// Like [`FileMutex::lock`], starting from `init()` if the file is missing or empty.
+    pub fn lock_or_else<Init: FnOnce() -> T>(
+        &self,
+        init: Init,
+    ) -> Result<FileMutexGuard<T>, FileMutexError> {
+        let mut lock = lock_path(&self.path, LockType::Exclusive, true)?;
+        let mut contents = Vec::new();
+        lock.read_to_end(&mut contents).map_err(sys::io_error)?;
+        let value = if contents.iter().all(u8::is_ascii_whitespace) {
+            init()
+        } else {
+            serde_json::from_slice(&contents).map_err(FileMutexError::Data)?
+        };
+        Ok(FileMutexGuard {
+            path: self.path.clone(),
+            lock: Some(lock),
+            value,
+            dirty: false,
+        })
+    }
+}
+impl<T> fmt::Debug for FileMutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FileMutex")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+/// The value read by [`FileMutex::lock`], with the file locked. Changes are written back when
+/// this is dropped, ignoring errors; [`save`](FileMutexGuard::save) reports them.
+pub struct FileMutexGuard<T: Serialize> {
+    path: PathBuf,
+    lock: Option<FdLock<File>>,
+    value: T,
+    dirty: bool,
+}
+impl<T: Serialize> FileMutexGuard<T> {
+    /// Writes the value back if it was changed, then releases the lock.
+    pub fn save(mut self) -> Result<(), FileMutexError> {
+        self.write_back()
+    }
+    /// Releases the lock without writing anything back.
+    pub fn discard(mut self) -> Result<(), Error> {
+        match self.lock.take() {
+            Some(lock) => lock.unlock().map(drop).map_err(Error::from),
+            None => Ok(()),
+        }
+    }
+    fn write_back(&mut self) -> Result<(), FileMutexError> {
+        let lock = match self.lock.take() {
+            Some(lock) => lock,
+            None => return Ok(()),
+        };
+        if self.dirty {
+            let contents = serde_json::to_vec(&self.value).map_err(FileMutexError::Data)?;
+            atomic::replace(&self.path, |f| {
+                f.write_all(&contents).map_err(sys::io_error)
+            })?;
+        }
+        lock.unlock().map_err(Error::from)?;
+        Ok(())
+    }
+}
+impl<T: Serialize> std::ops::Deref for FileMutexGuard<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+impl<T: Serialize> std::ops::DerefMut for FileMutexGuard<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.dirty = true;
+        &mut self.value
+    }
+}
+impl<T: Serialize> Drop for FileMutexGuard<T> {
+    fn drop(&mut self) {
+        let _ = self.write_back();
+    }
+}
+impl<T: Serialize + fmt::Debug> fmt::Debug for FileMutexGuard<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FileMutexGuard")
+            .field("path", &self.path)
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+/// Why a [`FileMutex`] operation failed.
+#[derive(Debug)]
+pub enum FileMutexError {
+    /// Locking, reading or writing the file failed.
+    Lock(Error),
+    /// The contents weren't valid JSON for the type, or the value couldn't be serialized.
+    Data(serde_json::Error),
+}
+impl From<Error> for FileMutexError {
+    fn from(e: Error) -> Self {
+        FileMutexError::Lock(e)
+    }
+}
+impl fmt::Display for FileMutexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileMutexError::Lock(e) => e.fmt(f),
+            FileMutexError::Data(e) => write!(f, "Invalid file contents: {}", e),
+        }
+    }
+}
+impl std::error::Error for FileMutexError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FileMutexError::Lock(e) => Some(e),
+            FileMutexError::Data(e) => Some(e),
+        }
+    }
+}
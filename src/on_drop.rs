@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use crate::Error;
+
+/// What dropping an [`FdLock`](crate::FdLock) does if releasing the lock fails, e.g. with
+/// [`Error::InvalidFd`] because the descriptor was closed behind the guard's back. The lock is
+/// gone either way once the handle is closed.
+#[derive(Clone)]
+pub enum OnDropError {
+    /// Carry on silently.
+    Ignore,
+    /// Print the error to stderr.
+    Log,
+    /// Panic, unless the thread is already panicking, in which case the error is logged instead
+    /// of aborting the process.
+    Panic,
+    /// Hand the error to a function.
+    Callback(Arc<dyn Fn(Error) + Send + Sync>),
+}
+impl OnDropError {
+    pub(crate) fn handle(&self, e: Error) {
+        match self {
+            OnDropError::Ignore => (),
+            OnDropError::Panic if !std::thread::panicking() => {
+                panic!("failed to release lock: {}", e)
+            }
+            OnDropError::Log | OnDropError::Panic => {
+                eprintln!("fd-lock-rs: failed to release lock: {}", e)
+            }
+            OnDropError::Callback(callback) => callback(e),
+        }
+    }
+}
+impl Default for OnDropError {
+    /// [`OnDropError::Ignore`].
+    fn default() -> Self {
+        OnDropError::Ignore
+    }
+}
+impl std::fmt::Debug for OnDropError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            OnDropError::Ignore => f.write_str("Ignore"),
+            OnDropError::Log => f.write_str("Log"),
+            OnDropError::Panic => f.write_str("Panic"),
+            OnDropError::Callback(_) => f.write_str("Callback(..)"),
+        }
+    }
+}
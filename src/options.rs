@@ -0,0 +1,133 @@
+use std::time::{Duration, Instant};
+
+use crate::backend::LockBackend;
+use crate::{AsFile, Error, FdLock, LockStyle, LockType, OnInterrupt, RetryPolicy};
+
+/// Settings for acquiring a lock, for when the positional arguments of [`FdLock::lock`] aren't
+/// enough, e.g. `LockOptions::exclusive().timeout(d).lock(f)`.
+///
+/// With the `serde` feature, fields missing when deserializing take the values of
+/// [`LockOptions::exclusive`].
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default = "LockOptions::exclusive"))]
+pub struct LockOptions {
+    lock_type: LockType,
+    blocking: bool,
+    timeout: Option<Duration>,
+    retry_policy: Option<RetryPolicy>,
+    poll_interval: Duration,
+    max_poll_interval: Duration,
+    on_interrupt: OnInterrupt,
+    style: LockStyle,
+    cloexec: Option<bool>,
+}
+impl LockOptions {
+    /// Blocking, with no timeout, using the default [`LockStyle`].
+    pub fn new(lock_type: LockType) -> Self {
+        LockOptions {
+            lock_type,
+            blocking: true,
+            timeout: None,
+            retry_policy: None,
+            poll_interval: Duration::from_millis(1),
+            max_poll_interval: Duration::from_millis(100),
+            on_interrupt: OnInterrupt::default(),
+            style: LockStyle::default(),
+            cloexec: None,
+        }
+    }
+    pub fn exclusive() -> Self {
+        Self::new(LockType::Exclusive)
+    }
+    pub fn shared() -> Self {
+        Self::new(LockType::Shared)
+    }
+    /// Fails with [`Error::WouldBlock`] instead of waiting if the lock is held. Ignored if a
+    /// timeout is set.
+    pub fn nonblocking(mut self) -> Self {
+        self.blocking = false;
+        self
+    }
+    /// Waits at most `timeout` for the lock, then fails with [`Error::Timeout`]. Without one,
+    /// waits indefinitely.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+    /// How long to wait before retrying, after the first attempt finds the lock held. Waits
+    /// double after each further failed attempt, up to [`LockOptions::max_poll_interval`].
+    /// Defaults to 1ms.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+    /// The longest wait between attempts. Defaults to 100ms.
+    pub fn max_poll_interval(mut self, interval: Duration) -> Self {
+        self.max_poll_interval = interval;
+        self
+    }
+    /// Replaces the poll intervals with an arbitrary [`RetryPolicy`].
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+    /// What a blocking wait does when interrupted by a signal. See [`OnInterrupt`].
+    pub fn on_interrupt(mut self, on_interrupt: OnInterrupt) -> Self {
+        self.on_interrupt = on_interrupt;
+        self
+    }
+    /// The kernel primitive [`LockOptions::lock`] uses, e.g. [`LockStyle::Ofd`].
+    pub fn style(mut self, style: LockStyle) -> Self {
+        self.style = style;
+        self
+    }
+    /// Sets or clears close-on-exec on the handle once it's locked, as with
+    /// [`FdLock::set_cloexec`]. Left as it is by default; std opens files close-on-exec.
+    pub fn cloexec(mut self, cloexec: bool) -> Self {
+        self.cloexec = Some(cloexec);
+        self
+    }
+    /// Locks `f` with the configured [`LockStyle`]. Waiting with a timeout retries
+    /// non-blocking attempts at the poll interval; waiting without one is a single blocking
+    /// call.
+    pub fn lock<F: AsFile>(&self, f: F) -> Result<FdLock<F>, Error> {
+        self.lock_with(f, self.style)
+    }
+    /// Like [`LockOptions::lock`], with an explicit backend instead of the configured style.
+    pub fn lock_with<F: AsFile, B: LockBackend>(
+        &self,
+        f: F,
+        backend: B,
+    ) -> Result<FdLock<F, B>, Error> {
+        let lock = match self.timeout {
+            None => FdLock::lock_interruptible(
+                f,
+                backend,
+                self.lock_type,
+                self.blocking,
+                self.on_interrupt,
+            ),
+            Some(timeout) => FdLock::lock_retry(
+                f,
+                backend,
+                self.lock_type,
+                &self.effective_retry_policy(),
+                Some(Instant::now() + timeout),
+            ),
+        }?;
+        if let Some(cloexec) = self.cloexec {
+            lock.set_cloexec(cloexec)?;
+        }
+        Ok(lock)
+    }
+    fn effective_retry_policy(&self) -> RetryPolicy {
+        match &self.retry_policy {
+            Some(policy) => policy.clone(),
+            None => RetryPolicy::Exponential {
+                initial: self.poll_interval,
+                max: self.max_poll_interval,
+            },
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::AsFile;
+
+/// What debug builds do on finding files locked in the opposite order to an earlier time: if
+/// one thread locked `a` and then `b` while holding `a`, and another later locks `a` while
+/// holding `b`, two such threads (or processes) can deadlock. Set with
+/// [`set_lock_order_check`].
+///
+/// Only guards from [`FdLock`](crate::FdLock) and the helpers built on it are tracked, as held
+/// by the thread that took the lock until it is released, from whichever thread. Release builds
+/// track nothing.
+///
+/// Files are told apart by device and inode number. What was seen about a file is forgotten
+/// once it's removed and the last guard on it goes, so a removed file's inode being reused
+/// doesn't look like the old file; one removed while nobody in the process held it is still
+/// remembered, though.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum LockOrderCheck {
+    /// Track nothing. The default.
+    #[default]
+    Off,
+    /// Print the two files to stderr.
+    Log,
+    /// Panic, after taking the lock (so the guard releases it as the panic unwinds).
+    Panic,
+}
+
+/// Sets what debug builds do on a lock order inversion, for the whole process.
+pub fn set_lock_order_check(check: LockOrderCheck) {
+    imp::set(check)
+}
+
+pub(crate) fn acquired<F: AsFile>(f: &F) {
+    imp::acquired(f)
+}
+
+pub(crate) fn released<F: AsFile>(f: &F) {
+    imp::released(f)
+}
+
+#[cfg(debug_assertions)]
+mod imp {
+    use std::collections::{HashMap, HashSet};
+    use std::sync::atomic::{AtomicU8, Ordering};
+    use std::sync::{Mutex, OnceLock};
+    use std::thread::{self, ThreadId};
+
+    use super::LockOrderCheck;
+    use crate::{sys, AsFile};
+
+    type Id = (u64, u64);
+
+    static CHECK: AtomicU8 = AtomicU8::new(LockOrderCheck::Off as u8);
+    static GRAPH: OnceLock<Mutex<Graph>> = OnceLock::new();
+
+    #[derive(Default)]
+    struct Graph {
+        // (a, b): `b` was locked while `a` was held.
+        seen: HashSet<(Id, Id)>,
+        // How many guards in the process, on any thread, hold each file.
+        holds: HashMap<Id, usize>,
+        // What each thread has locked, in order. Guards can be sent to and released on another
+        // thread, so this isn't thread-local.
+        held: HashMap<ThreadId, Vec<Id>>,
+    }
+
+    fn graph() -> std::sync::MutexGuard<'static, Graph> {
+        GRAPH
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set(check: LockOrderCheck) {
+        CHECK.store(check as u8, Ordering::Relaxed)
+    }
+
+    pub fn acquired<F: AsFile>(f: &F) {
+        let check = CHECK.load(Ordering::Relaxed);
+        if check == LockOrderCheck::Off as u8 {
+            return;
+        }
+        let id = match sys::file_id(f) {
+            Ok(id) => id,
+            Err(_) => return,
+        };
+        let inverted = {
+            let mut graph = graph();
+            let graph = &mut *graph;
+            *graph.holds.entry(id).or_insert(0) += 1;
+            let held = graph.held.entry(thread::current().id()).or_default();
+            let mut inverted = None;
+            for &before in held.iter().filter(|&&before| before != id) {
+                if graph.seen.contains(&(id, before)) {
+                    inverted = Some(before);
+                }
+                graph.seen.insert((before, id));
+            }
+            held.push(id);
+            inverted
+        };
+        if let Some(before) = inverted {
+            let msg = format!(
+                "lock order inversion: locked file {:?} while holding {:?}, which has been \
+                 locked while holding it before",
+                id, before
+            );
+            if check == LockOrderCheck::Panic as u8 {
+                panic!("{}", msg)
+            }
+            eprintln!("fd-lock-rs: {}", msg)
+        }
+    }
+
+    pub fn released<F: AsFile>(f: &F) {
+        if CHECK.load(Ordering::Relaxed) == LockOrderCheck::Off as u8 {
+            return;
+        }
+        let id = match sys::file_id(f) {
+            Ok(id) => id,
+            Err(_) => return,
+        };
+        let mut graph = graph();
+        // This thread's own hold on the file if it has one, else the one a guard sent here was
+        // taken under.
+        let me = thread::current().id();
+        let thread = if graph.held.get(&me).is_some_and(|held| held.contains(&id)) {
+            Some(me)
+        } else {
+            graph
+                .held
+                .iter()
+                .find(|(_, held)| held.contains(&id))
+                .map(|(&thread, _)| thread)
+        };
+        if let Some(thread) = thread {
+            let held = graph.held.get_mut(&thread).unwrap();
+            if let Some(i) = held.iter().rposition(|&held| held == id) {
+                held.remove(i);
+            }
+            if held.is_empty() {
+                graph.held.remove(&thread);
+            }
+        }
+        if let Some(holds) = graph.holds.get_mut(&id) {
+            *holds -= 1;
+            if *holds == 0 {
+                graph.holds.remove(&id);
+                if sys::unlinked(f) {
+                    graph.seen.retain(|&(a, b)| a != id && b != id);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(debug_assertions))]
+mod imp {
+    use super::LockOrderCheck;
+    use crate::AsFile;
+
+    pub fn set(_check: LockOrderCheck) {}
+
+    pub fn acquired<F: AsFile>(_f: &F) {}
+
+    pub fn released<F: AsFile>(_f: &F) {}
+}
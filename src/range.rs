@@ -0,0 +1,96 @@
+//! Byte-range record locks, for coordinating access to part of a file.
+
+use std::mem::ManuallyDrop;
+
+use crate::sys::{self, AsFile};
+use crate::{Error, LockStyle, LockType, OnDropError};
+
+/// A lock over `len` bytes of a file starting at `offset`. A `len` of 0 covers everything from
+/// `offset` to the end of the file, however far it grows.
+pub struct RangeLock<F: AsFile> {
+    inner: ManuallyDrop<F>,
+    style: LockStyle,
+    offset: u64,
+    len: u64,
+    on_drop: ManuallyDrop<OnDropError>,
+}
+impl<F: AsFile> std::ops::Deref for RangeLock<F> {
+    type Target = F;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+impl<F: AsFile> std::ops::DerefMut for RangeLock<F> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+impl<F: AsFile> RangeLock<F> {
+    /// Locks the range with `fcntl(2)` record locks, which belong to the process: they never
+    /// conflict with other ranges locked by the same process, and are all released as soon as
+    /// it closes *any* descriptor for the file.
+    pub fn lock(
+        f: F,
+        offset: u64,
+        len: u64,
+        lock_type: LockType,
+        blocking: bool,
+    ) -> Result<Self, Error> {
+        Self::lock_with_style(f, LockStyle::Fcntl, offset, len, lock_type, blocking)
+    }
+    /// Like [`RangeLock::lock`], but with an explicit [`LockStyle`]. `LockStyle::Flock` can't
+    /// lock ranges, and fails with [`Error::InvalidOperation`].
+    pub fn lock_with_style(
+        f: F,
+        style: LockStyle,
+        offset: u64,
+        len: u64,
+        lock_type: LockType,
+        blocking: bool,
+    ) -> Result<Self, Error> {
+        sys::lock_range(&f, &style, &lock_type, offset, len, blocking)?;
+        Ok(RangeLock {
+            inner: ManuallyDrop::new(f),
+            style,
+            offset,
+            len,
+            on_drop: ManuallyDrop::new(OnDropError::default()),
+        })
+    }
+    pub fn style(&self) -> LockStyle {
+        self.style
+    }
+    /// The locked `(offset, len)`.
+    pub fn range(&self) -> (u64, u64) {
+        (self.offset, self.len)
+    }
+    /// Sets what dropping the guard does if the release fails. Defaults to
+    /// [`OnDropError::Ignore`].
+    pub fn set_on_drop_error(&mut self, on_drop: OnDropError) {
+        *self.on_drop = on_drop;
+    }
+    pub fn unlock(self, blocking: bool) -> Result<F, (Self, Error)> {
+        match sys::unlock_range(&*self.inner, &self.style, self.offset, self.len, blocking) {
+            Ok(()) => {
+                let mut this = ManuallyDrop::new(self);
+                unsafe {
+                    ManuallyDrop::drop(&mut this.on_drop);
+                    Ok(ManuallyDrop::take(&mut this.inner))
+                }
+            }
+            Err(e) => Err((self, e)),
+        }
+    }
+}
+impl<F: AsFile> std::ops::Drop for RangeLock<F> {
+    fn drop(&mut self) {
+        let res = sys::unlock_range(&*self.inner, &self.style, self.offset, self.len, true);
+        let on_drop = unsafe {
+            ManuallyDrop::drop(&mut self.inner);
+            ManuallyDrop::take(&mut self.on_drop)
+        };
+        if let Err(e) = res {
+            on_drop.handle(e)
+        }
+    }
+}
@@ -0,0 +1,47 @@
+#[cfg(unix)]
+use std::os::unix::io::{AsFd, BorrowedFd};
+#[cfg(target_os = "wasi")]
+use std::os::wasi::io::{AsFd, BorrowedFd};
+#[cfg(windows)]
+use std::os::windows::io::{AsHandle, BorrowedHandle};
+
+use crate::sys::AsRawFile;
+
+/// Adapts a handle that only implements the raw handle traits ([`AsRawFile`]) so it can be
+/// locked.
+pub struct RawFile<T: AsRawFile>(T);
+impl<T: AsRawFile> RawFile<T> {
+    /// # Safety
+    ///
+    /// `inner`'s raw handle must stay open, and keep referring to the same file, for as long as
+    /// the `RawFile` exists.
+    pub unsafe fn new(inner: T) -> Self {
+        RawFile(inner)
+    }
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+impl<T: AsRawFile> std::ops::Deref for RawFile<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl<T: AsRawFile> std::ops::DerefMut for RawFile<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+#[cfg(any(unix, target_os = "wasi"))]
+impl<T: AsRawFile> AsFd for RawFile<T> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        unsafe { BorrowedFd::borrow_raw(self.0.as_raw_fd()) }
+    }
+}
+#[cfg(windows)]
+impl<T: AsRawFile> AsHandle for RawFile<T> {
+    fn as_handle(&self) -> BorrowedHandle<'_> {
+        unsafe { BorrowedHandle::borrow_raw(self.0.as_raw_handle()) }
+    }
+}
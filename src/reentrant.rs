@@ -0,0 +1,127 @@
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+
+use crate::backend::LockBackend;
+use crate::{AsFile, Error, LockStyle, LockType};
+
+/// Who can take a [`ReentrantFileLock`] again while it is held.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Reentrancy {
+    /// Any thread in the process; the lock only keeps out other processes.
+    Process,
+    /// Only the holding thread; others wait for it to let go entirely, like a reentrant mutex.
+    Thread,
+}
+
+/// A file lock that can be taken again while already held, counting the guards: only the first
+/// [`lock`](ReentrantFileLock::lock) asks the kernel for the lock and only dropping the last
+/// guard releases it. For code that locks the file at several levels of the call stack.
+#[derive(Debug)]
+pub struct ReentrantFileLock<F: AsFile, B: LockBackend = LockStyle> {
+    f: F,
+    backend: B,
+    lock_type: LockType,
+    reentrancy: Reentrancy,
+    state: Mutex<State>,
+    changed: Condvar,
+}
+#[derive(Debug, Default)]
+struct State {
+    count: usize,
+    owner: Option<ThreadId>,
+    // A thread is asking the kernel for the lock, without holding `state` while it waits.
+    acquiring: bool,
+}
+impl<F: AsFile> ReentrantFileLock<F> {
+    /// Uses the default [`LockStyle`].
+    pub fn new(f: F, lock_type: LockType, reentrancy: Reentrancy) -> Self {
+        Self::with_backend(f, LockStyle::default(), lock_type, reentrancy)
+    }
+}
+impl<F: AsFile, B: LockBackend> ReentrantFileLock<F, B> {
+    pub fn with_backend(f: F, backend: B, lock_type: LockType, reentrancy: Reentrancy) -> Self {
+        ReentrantFileLock {
+            f,
+            backend,
+            lock_type,
+            reentrancy,
+            state: Mutex::new(State::default()),
+            changed: Condvar::new(),
+        }
+    }
+    /// Takes the lock, or another count on it, waiting if needed.
+    pub fn lock(&self) -> Result<ReentrantGuard<'_, F, B>, Error> {
+        self.acquire(true)
+    }
+    /// Like [`ReentrantFileLock::lock`], failing with [`Error::WouldBlock`] instead of waiting.
+    pub fn try_lock(&self) -> Result<ReentrantGuard<'_, F, B>, Error> {
+        self.acquire(false)
+    }
+    /// How many guards are currently out.
+    pub fn count(&self) -> usize {
+        self.state().count
+    }
+    pub fn lock_type(&self) -> LockType {
+        self.lock_type
+    }
+    pub fn get_ref(&self) -> &F {
+        &self.f
+    }
+    pub fn into_inner(self) -> F {
+        self.f
+    }
+    fn acquire(&self, blocking: bool) -> Result<ReentrantGuard<'_, F, B>, Error> {
+        let me = thread::current().id();
+        let mut state = self.state();
+        while state.acquiring
+            || (self.reentrancy == Reentrancy::Thread
+                && state.owner.is_some_and(|owner| owner != me))
+        {
+            if !blocking {
+                return Err(Error::WouldBlock);
+            }
+            state = self.changed.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        if state.count == 0 {
+            state.acquiring = true;
+            drop(state);
+            let res = if blocking {
+                self.backend.acquire(&self.f, &self.lock_type)
+            } else {
+                self.backend.try_acquire(&self.f, &self.lock_type)
+            };
+            state = self.state();
+            state.acquiring = false;
+            self.changed.notify_all();
+            res?;
+            state.owner = Some(me);
+        }
+        state.count += 1;
+        Ok(ReentrantGuard(self))
+    }
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// One count on a [`ReentrantFileLock`]. Releases the file lock if it's the last.
+#[derive(Debug)]
+pub struct ReentrantGuard<'a, F: AsFile, B: LockBackend = LockStyle>(&'a ReentrantFileLock<F, B>);
+
+impl<'a, F: AsFile, B: LockBackend> std::ops::Deref for ReentrantGuard<'a, F, B> {
+    type Target = F;
+    fn deref(&self) -> &Self::Target {
+        &self.0.f
+    }
+}
+impl<'a, F: AsFile, B: LockBackend> Drop for ReentrantGuard<'a, F, B> {
+    fn drop(&mut self) {
+        let mut state = self.0.state();
+        state.count -= 1;
+        if state.count == 0 {
+            let _ = self.0.backend.release(&self.0.f);
+            state.owner = None;
+            self.0.changed.notify_all();
+        }
+    }
+}
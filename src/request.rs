@@ -0,0 +1,88 @@
+use std::io::{Read, Write};
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::sync::{Arc, Mutex};
+
+use crate::backend::LockBackend;
+use crate::{AsFile, Error, FdLock, LockStyle, LockType};
+
+struct Shared<F: AsFile, B: LockBackend> {
+    result: Option<Result<FdLock<F, B>, Error>>,
+    cancelled: bool,
+}
+
+/// A blocking lock acquisition running on a helper thread, for event loops that multiplex on raw
+/// descriptors (mio, epoll, poll). The request's descriptor becomes readable once the attempt
+/// has finished, at which point [`LockRequest::try_take`] returns its result. Register it with
+/// e.g. `mio::unix::SourceFd(&request.as_raw_fd())`.
+///
+/// Dropping an unfinished request abandons it: the helper thread releases the lock as soon as
+/// it's acquired.
+pub struct LockRequest<F: AsFile + Send + 'static, B: LockBackend + Send + 'static = LockStyle> {
+    shared: Arc<Mutex<Shared<F, B>>>,
+    ready: UnixStream,
+}
+impl<F: AsFile + Send + 'static> LockRequest<F> {
+    /// Starts locking `f` with the default [`LockStyle`].
+    pub fn new(f: F, lock_type: LockType) -> Result<Self, Error> {
+        Self::with_backend(f, LockStyle::default(), lock_type)
+    }
+}
+impl<F: AsFile + Send + 'static, B: LockBackend + Send + 'static> LockRequest<F, B> {
+    pub fn with_backend(f: F, backend: B, lock_type: LockType) -> Result<Self, Error> {
+        let (ready, mut notify) = UnixStream::pair().map_err(crate::sys::io_error)?;
+        ready.set_nonblocking(true).map_err(crate::sys::io_error)?;
+        let shared = Arc::new(Mutex::new(Shared {
+            result: None,
+            cancelled: false,
+        }));
+        let thread_shared = shared.clone();
+        std::thread::Builder::new()
+            .name("fd-lock-request".into())
+            .spawn(move || {
+                let result = FdLock::lock_with(f, backend, lock_type, true);
+                let mut shared = thread_shared.lock().unwrap();
+                if shared.cancelled {
+                    drop(shared);
+                    drop(result);
+                } else {
+                    shared.result = Some(result);
+                    drop(shared);
+                    let _ = notify.write_all(&[1]);
+                }
+            })
+            .map_err(crate::sys::io_error)?;
+        Ok(LockRequest { shared, ready })
+    }
+    /// Returns the result of the attempt if it has finished, or `None` if it's still waiting.
+    /// After the result has been taken, this always returns `None`.
+    pub fn try_take(&mut self) -> Option<Result<FdLock<F, B>, Error>> {
+        let result = self.shared.lock().unwrap().result.take();
+        if result.is_some() {
+            let _ = self.ready.read(&mut [0]);
+        }
+        result
+    }
+}
+impl<F: AsFile + Send + 'static, B: LockBackend + Send + 'static> AsRawFd for LockRequest<F, B> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.ready.as_raw_fd()
+    }
+}
+impl<F: AsFile + Send + 'static, B: LockBackend + Send + 'static> AsFd for LockRequest<F, B> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.ready.as_fd()
+    }
+}
+impl<F: AsFile + Send + 'static, B: LockBackend + Send + 'static> std::ops::Drop
+    for LockRequest<F, B>
+{
+    fn drop(&mut self) {
+        let result = {
+            let mut shared = self.shared.lock().unwrap();
+            shared.cancelled = true;
+            shared.result.take()
+        };
+        drop(result);
+    }
+}
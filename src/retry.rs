@@ -0,0 +1,102 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long to wait between attempts when retrying a contended lock.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum RetryPolicy {
+    /// The same wait every time.
+    Fixed(Duration),
+    /// `initial`, doubling after each failed attempt up to `max`.
+    Exponential { initial: Duration, max: Duration },
+    /// Like `Exponential`, but each wait is a random duration up to the exponential one, so
+    /// many waiters started together don't all retry in lockstep.
+    Jittered { initial: Duration, max: Duration },
+    /// Computes the wait from the number of attempts that have failed so far (starting at 1).
+    /// Can't be serialized.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(Arc<dyn Fn(u32) -> Duration + Send + Sync>),
+}
+impl RetryPolicy {
+    /// The wait after `failures` attempts have failed.
+    pub fn delay(&self, failures: u32) -> Duration {
+        match self {
+            RetryPolicy::Fixed(delay) => *delay,
+            RetryPolicy::Exponential { initial, max } => exponential(*initial, *max, failures),
+            RetryPolicy::Jittered { initial, max } => {
+                let delay = exponential(*initial, *max, failures);
+                let mut hasher = RandomState::new().build_hasher();
+                hasher.write_u32(failures);
+                delay.mul_f64(hasher.finish() as f64 / u64::MAX as f64)
+            }
+            RetryPolicy::Custom(delay) => delay(failures),
+        }
+    }
+}
+impl Default for RetryPolicy {
+    /// Exponential, from 1ms up to 100ms.
+    fn default() -> Self {
+        RetryPolicy::Exponential {
+            initial: Duration::from_millis(1),
+            max: Duration::from_millis(100),
+        }
+    }
+}
+impl std::fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RetryPolicy::Fixed(delay) => f.debug_tuple("Fixed").field(delay).finish(),
+            RetryPolicy::Exponential { initial, max } => f
+                .debug_struct("Exponential")
+                .field("initial", initial)
+                .field("max", max)
+                .finish(),
+            RetryPolicy::Jittered { initial, max } => f
+                .debug_struct("Jittered")
+                .field("initial", initial)
+                .field("max", max)
+                .finish(),
+            RetryPolicy::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// What a blocking acquisition does when a signal interrupts it (`EINTR`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum OnInterrupt {
+    /// Go back to waiting.
+    Retry,
+    /// Fail with [`Error::Interrupted`](crate::Error::Interrupted).
+    Return,
+    /// Go back to waiting if the deadline hasn't passed, or fail with
+    /// [`Error::Interrupted`](crate::Error::Interrupted) if it has. Can't be serialized.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    RetryUntil(Instant),
+}
+impl OnInterrupt {
+    pub(crate) fn retry(&self) -> bool {
+        match self {
+            OnInterrupt::Retry => true,
+            OnInterrupt::Return => false,
+            OnInterrupt::RetryUntil(deadline) => Instant::now() < *deadline,
+        }
+    }
+}
+impl Default for OnInterrupt {
+    /// `Return`, which is what [`FdLock::lock`](crate::FdLock::lock) does.
+    fn default() -> Self {
+        OnInterrupt::Return
+    }
+}
+
+fn exponential(initial: Duration, max: Duration, failures: u32) -> Duration {
+    let factor = 1u32
+        .checked_shl(failures.saturating_sub(1))
+        .unwrap_or(u32::MAX);
+    std::cmp::min(initial.saturating_mul(factor), max)
+}
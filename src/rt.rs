@@ -0,0 +1,135 @@
+use std::sync::{Arc, Mutex};
+
+use crate::backend::LockBackend;
+use crate::{AsFile, Error, FdLock, LockStyle, LockType, UnlockError};
+
+// Runs `f` on the runtime's blocking thread pool. Fails with `Error::Other` if the runtime shut
+// down before `f` could run; a panic in `f` carries on in the caller.
+#[cfg(feature = "tokio")]
+async fn spawn_blocking<R, Func>(f: Func) -> Result<R, Error>
+where
+    R: Send + 'static,
+    Func: FnOnce() -> R + Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(r) => Ok(r),
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(Error::Other(e.into())),
+    }
+}
+
+#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+async fn spawn_blocking<R, Func>(f: Func) -> Result<R, Error>
+where
+    R: Send + 'static,
+    Func: FnOnce() -> R + Send + 'static,
+{
+    Ok(async_std::task::spawn_blocking(f).await)
+}
+
+#[cfg(all(
+    feature = "blocking",
+    not(any(feature = "tokio", feature = "async-std"))
+))]
+async fn spawn_blocking<R, Func>(f: Func) -> Result<R, Error>
+where
+    R: Send + 'static,
+    Func: FnOnce() -> R + Send + 'static,
+{
+    Ok(blocking::unblock(f).await)
+}
+
+struct Shared<T, R> {
+    input: Option<T>,
+    output: Option<R>,
+    cancelled: bool,
+}
+
+// Marks the call cancelled when the future awaiting it is dropped, and drops any result that
+// was produced but never collected.
+struct CancelOnDrop<T, R>(Arc<Mutex<Shared<T, R>>>);
+impl<T, R> Drop for CancelOnDrop<T, R> {
+    fn drop(&mut self) {
+        let output = {
+            let mut shared = self.0.lock().unwrap();
+            shared.cancelled = true;
+            shared.output.take()
+        };
+        drop(output);
+    }
+}
+
+// Runs `f(value)` on the async runtime's blocking thread pool, so waiting for a contended lock
+// doesn't stall the executor. If the runtime shuts down before `f` gets to run, `value` is
+// handed back untouched.
+//
+// Dropping the returned future cancels the call: if `f` hasn't started it never will, and if it
+// is already running its result is dropped, on the blocking thread, as soon as it returns. So a
+// cancelled `lock_async` never leaves the lock held.
+async fn unblock<T, R, Func>(value: T, f: Func) -> Result<R, (T, Error)>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    Func: FnOnce(T) -> R + Send + 'static,
+{
+    let shared = Arc::new(Mutex::new(Shared {
+        input: Some(value),
+        output: None,
+        cancelled: false,
+    }));
+    let guard = CancelOnDrop(shared.clone());
+    let res = spawn_blocking(move || {
+        let input = {
+            let mut shared = shared.lock().unwrap();
+            if shared.cancelled {
+                return;
+            }
+            shared.input.take().unwrap()
+        };
+        let output = f(input);
+        let mut shared = shared.lock().unwrap();
+        if shared.cancelled {
+            drop(shared);
+            drop(output);
+        } else {
+            shared.output = Some(output);
+        }
+    })
+    .await;
+    let mut shared = guard.0.lock().unwrap();
+    match res {
+        Ok(()) => Ok(shared.output.take().unwrap()),
+        Err(e) => Err((shared.input.take().unwrap(), e)),
+    }
+}
+
+impl<F: AsFile + Send + 'static> FdLock<F> {
+    /// Like [`FdLock::lock`] with `blocking` set, but waits for the lock off the executor. See
+    /// [`FdLock::lock_with_async`] for what happens on cancellation.
+    pub async fn lock_async(f: F, lock_type: LockType) -> Result<Self, Error> {
+        Self::lock_with_async(f, LockStyle::default(), lock_type).await
+    }
+}
+impl<F: AsFile + Send + 'static, B: LockBackend + Send + 'static> FdLock<F, B> {
+    /// Like [`FdLock::lock_with`] with `blocking` set, but waits for the lock off the executor.
+    ///
+    /// Cancellation safe: if the future is dropped before it resolves, the pending call is
+    /// either never started or releases the lock the moment it's acquired.
+    pub async fn lock_with_async(f: F, backend: B, lock_type: LockType) -> Result<Self, Error> {
+        unblock((f, backend), move |(f, backend)| {
+            Self::lock_with(f, backend, lock_type, true)
+        })
+        .await
+        .map_err(|(_, e)| e)?
+    }
+    /// Like [`FdLock::unlock`], but releases the lock off the executor and resolves to the inner
+    /// handle. Releasing can block on network filesystems, and on backends like
+    /// [`DotLock`](crate::backend::DotLock) whose release does file I/O, so prefer this to
+    /// dropping the guard from async code.
+    pub async fn unlock_async(self) -> Result<F, UnlockError<F, B>> {
+        match unblock(self, FdLock::unlock).await {
+            Ok(res) => res,
+            Err((lock, e)) => Err(UnlockError::new(lock, e)),
+        }
+    }
+}
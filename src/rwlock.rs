@@ -0,0 +1,184 @@
+use std::mem::ManuallyDrop;
+use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError};
+
+use crate::backend::LockBackend;
+use crate::{AsFile, Error, LockStyle, LockType};
+
+/// A reader-writer lock on a file, shared between processes: [`read`](FileRwLock::read) takes
+/// a shared lock and [`write`](FileRwLock::write) an exclusive one, each returning a guard that
+/// releases it when dropped.
+///
+/// Within a process it behaves like [`std::sync::RwLock`]: any number of threads can read at
+/// once, all under one shared lock on the file that the first reader takes and the last one
+/// releases, and a writer waits for them to finish.
+#[derive(Debug)]
+pub struct FileRwLock<F: AsFile, B: LockBackend = LockStyle> {
+    f: RwLock<F>,
+    backend: B,
+    // How many read guards there are. The file is locked while this isn't zero.
+    readers: Mutex<usize>,
+}
+impl<F: AsFile> FileRwLock<F> {
+    /// Uses the default [`LockStyle`].
+    pub fn new(f: F) -> Self {
+        Self::with_backend(f, LockStyle::default())
+    }
+}
+impl<F: AsFile, B: LockBackend> FileRwLock<F, B> {
+    pub fn with_backend(f: F, backend: B) -> Self {
+        FileRwLock {
+            f: RwLock::new(f),
+            backend,
+            readers: Mutex::new(0),
+        }
+    }
+    /// Waits for a shared lock.
+    pub fn read(&self) -> Result<FileReadGuard<'_, F, B>, Error> {
+        self.acquire_read(true)
+    }
+    /// Takes a shared lock if it is available right away.
+    pub fn try_read(&self) -> Result<Option<FileReadGuard<'_, F, B>>, Error> {
+        some(self.acquire_read(false))
+    }
+    /// Waits for an exclusive lock.
+    pub fn write(&self) -> Result<FileWriteGuard<'_, F, B>, Error> {
+        self.acquire_write(true)
+    }
+    /// Takes an exclusive lock if it is available right away.
+    pub fn try_write(&self) -> Result<Option<FileWriteGuard<'_, F, B>>, Error> {
+        some(self.acquire_write(false))
+    }
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+    pub fn get_mut(&mut self) -> &mut F {
+        self.f.get_mut().unwrap_or_else(|e| e.into_inner())
+    }
+    pub fn into_inner(self) -> F {
+        self.f.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+    // A panic while a guard was held leaves nothing inconsistent here; the file lock was
+    // released by the guard's drop.
+    fn acquire_read(&self, blocking: bool) -> Result<FileReadGuard<'_, F, B>, Error> {
+        let f = if blocking {
+            self.f.read().unwrap_or_else(|e| e.into_inner())
+        } else {
+            match self.f.try_read() {
+                Ok(f) => f,
+                Err(TryLockError::Poisoned(e)) => e.into_inner(),
+                Err(TryLockError::WouldBlock) => return Err(Error::WouldBlock),
+            }
+        };
+        let mut readers = self.readers();
+        if *readers == 0 {
+            self.lock_file(&f, LockType::Shared, blocking)?;
+        }
+        *readers += 1;
+        Ok(FileReadGuard {
+            f: ManuallyDrop::new(f),
+            lock: self,
+        })
+    }
+    fn acquire_write(&self, blocking: bool) -> Result<FileWriteGuard<'_, F, B>, Error> {
+        let f = if blocking {
+            self.f.write().unwrap_or_else(|e| e.into_inner())
+        } else {
+            match self.f.try_write() {
+                Ok(f) => f,
+                Err(TryLockError::Poisoned(e)) => e.into_inner(),
+                Err(TryLockError::WouldBlock) => return Err(Error::WouldBlock),
+            }
+        };
+        self.lock_file(&f, LockType::Exclusive, blocking)?;
+        Ok(FileWriteGuard {
+            f: ManuallyDrop::new(f),
+            backend: &self.backend,
+        })
+    }
+    fn lock_file(&self, f: &F, lock_type: LockType, blocking: bool) -> Result<(), Error> {
+        if blocking {
+            self.backend.acquire(f, &lock_type)
+        } else {
+            self.backend.try_acquire(f, &lock_type)
+        }
+    }
+    fn readers(&self) -> MutexGuard<'_, usize> {
+        self.readers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn some<T>(res: Result<T, Error>) -> Result<Option<T>, Error> {
+    match res {
+        Ok(t) => Ok(Some(t)),
+        Err(Error::WouldBlock) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// A shared lock from [`FileRwLock::read`]. Only hands out `&F`.
+pub struct FileReadGuard<'a, F: AsFile, B: LockBackend = LockStyle> {
+    // Only taken out by `unlock` and `drop`, once the file lock is dealt with.
+    f: ManuallyDrop<RwLockReadGuard<'a, F>>,
+    lock: &'a FileRwLock<F, B>,
+}
+impl<'a, F: AsFile, B: LockBackend> FileReadGuard<'a, F, B> {
+    // The last reader out releases the file lock, before letting writers in.
+    fn release(&self) -> Result<(), Error> {
+        let mut readers = self.lock.readers();
+        *readers -= 1;
+        if *readers == 0 {
+            self.lock.backend.release(&**self.f)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// An exclusive lock from [`FileRwLock::write`]. Hands out `&mut F`.
+pub struct FileWriteGuard<'a, F: AsFile, B: LockBackend = LockStyle> {
+    // Only taken out by `unlock` and `drop`, once the file lock is released.
+    f: ManuallyDrop<RwLockWriteGuard<'a, F>>,
+    backend: &'a B,
+}
+impl<'a, F: AsFile, B: LockBackend> FileWriteGuard<'a, F, B> {
+    fn release(&self) -> Result<(), Error> {
+        self.backend.release(&**self.f)
+    }
+}
+
+macro_rules! guard {
+    ($guard:ident, $lock_type:expr) => {
+        impl<'a, F: AsFile, B: LockBackend> $guard<'a, F, B> {
+            pub fn lock_type(&self) -> LockType {
+                $lock_type
+            }
+            /// Releases the lock, reporting any error that drop would ignore.
+            pub fn unlock(self) -> Result<(), Error> {
+                let mut this = ManuallyDrop::new(self);
+                let res = this.release();
+                unsafe { ManuallyDrop::drop(&mut this.f) };
+                res
+            }
+        }
+        impl<'a, F: AsFile, B: LockBackend> std::ops::Deref for $guard<'a, F, B> {
+            type Target = F;
+            fn deref(&self) -> &Self::Target {
+                &self.f
+            }
+        }
+        impl<'a, F: AsFile, B: LockBackend> Drop for $guard<'a, F, B> {
+            fn drop(&mut self) {
+                let _ = self.release();
+                unsafe { ManuallyDrop::drop(&mut self.f) };
+            }
+        }
+    };
+}
+guard!(FileReadGuard, LockType::Shared);
+guard!(FileWriteGuard, LockType::Exclusive);
+
+impl<'a, F: AsFile, B: LockBackend> std::ops::DerefMut for FileWriteGuard<'a, F, B> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.f
+    }
+}
@@ -0,0 +1,49 @@
+use std::io::Write;
+
+use crate::backend::LockBackend;
+use crate::sys::{self, AsFile};
+use crate::{Error, FdLock, LockStyle, LockType};
+
+/// Locks `f` with the default [`LockStyle`] for the duration of `func`. See
+/// [`with_lock_with`].
+pub fn with_lock<F, T, E, Func>(
+    f: F,
+    lock_type: LockType,
+    blocking: bool,
+    func: Func,
+) -> Result<T, E>
+where
+    F: AsFile + Write,
+    E: From<Error>,
+    Func: FnOnce(&mut F) -> Result<T, E>,
+{
+    with_lock_with(f, LockStyle::default(), lock_type, blocking, func)
+}
+
+/// Locks `f`, runs `func` on it, then flushes and releases it, whether or not `func` succeeded.
+/// Pass a reference such as `&File` to keep the handle afterwards.
+///
+/// An error from `func` is returned in preference to one from flushing, which is returned in
+/// preference to one from releasing.
+pub fn with_lock_with<F, B, T, E, Func>(
+    f: F,
+    backend: B,
+    lock_type: LockType,
+    blocking: bool,
+    func: Func,
+) -> Result<T, E>
+where
+    F: AsFile + Write,
+    B: LockBackend,
+    E: From<Error>,
+    Func: FnOnce(&mut F) -> Result<T, E>,
+{
+    let mut lock = FdLock::lock_with(f, backend, lock_type, blocking)?;
+    let res = func(&mut lock);
+    let flushed = lock.flush();
+    let released = lock.unlock();
+    let res = res?;
+    flushed.map_err(sys::io_error)?;
+    released.map_err(Error::from)?;
+    Ok(res)
+}
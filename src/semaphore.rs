@@ -0,0 +1,111 @@
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::{sys, Error, LockStyle, LockType};
+
+// Record locks that belong to the open file description where there are any, so taking and
+// releasing permits doesn't depend on fcntl(2)'s per-process bookkeeping.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const STYLE: LockStyle = LockStyle::Ofd;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const STYLE: LockStyle = LockStyle::Fcntl;
+
+/// A counting semaphore shared between processes: at most `permits` [`Permit`]s are out at a
+/// time across everyone using the file at `path`. Permit `i` is an exclusive lock on byte `i`
+/// of the file, so a process's permits are given back by the kernel when it exits.
+///
+/// Every user must pass the same `permits`. Waiting for a permit polls (backing off up to
+/// 50ms), since there's no way to wait on the first of several locks; within a process a
+/// returned permit wakes a waiter right away.
+///
+/// On Linux the locks are open file description locks. Elsewhere they are fcntl(2) locks,
+/// which the process loses all of when it closes *any* descriptor for the file, so keep to one
+/// `FileSemaphore` per file per process there.
+#[derive(Debug)]
+pub struct FileSemaphore {
+    f: File,
+    taken: Mutex<Vec<bool>>,
+    returned: Condvar,
+}
+impl FileSemaphore {
+    /// Opens (creating if needed) the file at `path`. Zero `permits` fails with
+    /// [`Error::InvalidOperation`].
+    pub fn new<P: AsRef<Path>>(path: P, permits: usize) -> Result<Self, Error> {
+        if permits == 0 {
+            return Err(Error::InvalidOperation);
+        }
+        let f = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(sys::io_error)?;
+        Ok(FileSemaphore {
+            f,
+            taken: Mutex::new(vec![false; permits]),
+            returned: Condvar::new(),
+        })
+    }
+    pub fn permits(&self) -> usize {
+        self.taken().len()
+    }
+    /// Waits for a permit.
+    pub fn acquire(&self) -> Result<Permit<'_>, Error> {
+        let mut wait = Duration::from_millis(1);
+        loop {
+            if let Some(permit) = self.try_acquire()? {
+                return Ok(permit);
+            }
+            let taken = self.taken();
+            let _ = self.returned.wait_timeout(taken, wait);
+            wait = (wait * 2).min(Duration::from_millis(50));
+        }
+    }
+    /// Takes a permit if one is free right away.
+    pub fn try_acquire(&self) -> Result<Option<Permit<'_>>, Error> {
+        let mut taken = self.taken();
+        for i in 0..taken.len() {
+            if taken[i] {
+                continue;
+            }
+            match sys::lock_range(&self.f, &STYLE, &LockType::Exclusive, i as u64, 1, false) {
+                Ok(()) => {
+                    taken[i] = true;
+                    return Ok(Some(Permit { sem: self, i }));
+                }
+                Err(Error::WouldBlock) => (),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(None)
+    }
+    fn taken(&self) -> MutexGuard<'_, Vec<bool>> {
+        self.taken.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A permit from a [`FileSemaphore`], given back when dropped.
+#[derive(Debug)]
+pub struct Permit<'a> {
+    sem: &'a FileSemaphore,
+    i: usize,
+}
+impl Permit<'_> {
+    /// Which permit this is, from 0 to `permits - 1`.
+    pub fn index(&self) -> usize {
+        self.i
+    }
+}
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let mut taken = self.sem.taken();
+        // If this fails the byte stays locked, and the permit is lost until the file is closed.
+        if sys::unlock_range(&self.sem.f, &STYLE, self.i as u64, 1, true).is_ok() {
+            taken[self.i] = false;
+        }
+        self.sem.returned.notify_one();
+    }
+}
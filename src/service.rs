@@ -0,0 +1,214 @@
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::Instant;
+
+use crate::backend::LockBackend;
+use crate::future::wake_at;
+use crate::{AsFile, Error, FdLock, LockStyle, LockType, RetryPolicy};
+
+// One attempt at a request, returning when to try again if the lock was taken.
+type Job = Box<dyn FnMut() -> Option<Instant> + Send>;
+
+// A request, with the way back onto the queue for its next attempt.
+struct Queued {
+    job: Job,
+    queue: Sender<Queued>,
+}
+
+/// A fixed pool of worker threads that take locks on behalf of their callers, for programs
+/// waiting on more lock files than they'd want threads for.
+///
+/// Workers never wait on a lock. Each turn of a request is one non-blocking attempt; if the
+/// lock is held, the request goes back on the queue after a backoff (the default
+/// [`RetryPolicy`], timed by the same thread [`LockFuture`](crate::LockFuture) uses), so
+/// requests for free locks never wait behind contended ones.
+///
+/// Dropping the service lets the workers exit once every request has finished.
+pub struct LockService {
+    queue: Sender<Queued>,
+}
+impl LockService {
+    /// Starts a pool of `workers` threads (at least one).
+    pub fn new(workers: usize) -> Result<Self, Error> {
+        let (send, recv) = mpsc::channel::<Queued>();
+        let recv = Arc::new(Mutex::new(recv));
+        for _ in 0..workers.max(1) {
+            let recv = recv.clone();
+            std::thread::Builder::new()
+                .name("fd-lock-service".into())
+                .spawn(move || work(&recv))
+                .map_err(crate::sys::io_error)?;
+        }
+        Ok(LockService { queue: send })
+    }
+    /// Queues a lock of `f` with the default [`LockStyle`].
+    pub fn lock<F: AsFile + Send + 'static>(&self, f: F, lock_type: LockType) -> PendingLock<F> {
+        self.lock_with(f, LockStyle::default(), lock_type)
+    }
+    pub fn lock_with<F: AsFile + Send + 'static, B: LockBackend + Send + 'static>(
+        &self,
+        f: F,
+        backend: B,
+        lock_type: LockType,
+    ) -> PendingLock<F, B> {
+        let slot = Arc::new(Slot {
+            state: Mutex::new(State {
+                result: None,
+                waker: None,
+                cancelled: false,
+            }),
+            done: Condvar::new(),
+        });
+        let job_slot = slot.clone();
+        let policy = RetryPolicy::default();
+        let mut inner = Some((f, backend));
+        let mut failures = 0;
+        let job: Job = Box::new(move || {
+            // Cancelled while queued: the handle is dropped along with the job.
+            if lock(&job_slot.state).cancelled {
+                return None;
+            }
+            let (f, backend) = inner.take()?;
+            // A panicking backend fails this request, rather than the worker and every request
+            // queued behind it.
+            let result =
+                match panic::catch_unwind(AssertUnwindSafe(|| backend.try_acquire(&f, &lock_type)))
+                {
+                    Ok(Ok(())) => Ok(FdLock::assume_locked(f, backend, lock_type)),
+                    Ok(Err(Error::WouldBlock)) => {
+                        inner = Some((f, backend));
+                        failures += 1;
+                        return Some(Instant::now() + policy.delay(failures));
+                    }
+                    Ok(Err(e)) => Err(e),
+                    Err(_) => Err(Error::Interrupted),
+                };
+            // Releases the lock again if the request was cancelled during the attempt.
+            job_slot.finish(result);
+            None
+        });
+        let queued = Queued {
+            job,
+            queue: self.queue.clone(),
+        };
+        if self.queue.send(queued).is_err() {
+            // No workers left to take it.
+            slot.finish(Err(Error::Interrupted));
+        }
+        PendingLock { slot }
+    }
+}
+
+fn work(recv: &Mutex<Receiver<Queued>>) {
+    loop {
+        let mut queued = match lock(recv).recv() {
+            Ok(queued) => queued,
+            Err(_) => return,
+        };
+        if let Some(at) = (queued.job)() {
+            let requeue = Arc::new(Requeue(Mutex::new(Some(queued))));
+            if wake_at(at, Waker::from(requeue.clone())).is_err() {
+                // No timer thread to be had, so this worker waits out the backoff instead.
+                std::thread::sleep(at.saturating_duration_since(Instant::now()));
+                requeue.wake();
+            }
+        }
+    }
+}
+
+// Puts a request back on the queue when the timer fires.
+struct Requeue(Mutex<Option<Queued>>);
+impl Wake for Requeue {
+    fn wake(self: Arc<Self>) {
+        if let Some(queued) = lock(&self.0).take() {
+            let queue = queued.queue.clone();
+            let _ = queue.send(queued);
+        }
+    }
+}
+
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+struct State<R> {
+    result: Option<R>,
+    waker: Option<Waker>,
+    cancelled: bool,
+}
+struct Slot<R> {
+    state: Mutex<State<R>>,
+    done: Condvar,
+}
+impl<R> Slot<R> {
+    fn finish(&self, result: R) {
+        let mut state = lock(&self.state);
+        if state.cancelled {
+            drop(state);
+            drop(result);
+            return;
+        }
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        self.done.notify_all();
+    }
+}
+
+/// A lock queued on a [`LockService`]. Await it, or block on [`PendingLock::wait`]. Dropping it
+/// cancels the request: if a worker has already taken the lock, it's released right away, and
+/// otherwise no further attempt is made.
+///
+/// If the backend panics while taking the lock, the request fails with [`Error::Interrupted`]
+/// and the worker carries on with the next one.
+pub struct PendingLock<F: AsFile, B: LockBackend = LockStyle> {
+    slot: Arc<Slot<Result<FdLock<F, B>, Error>>>,
+}
+impl<F: AsFile, B: LockBackend> PendingLock<F, B> {
+    /// Returns the result if the request has finished.
+    pub fn try_take(&mut self) -> Option<Result<FdLock<F, B>, Error>> {
+        lock(&self.slot.state).result.take()
+    }
+    /// Blocks the current thread until the request finishes.
+    pub fn wait(self) -> Result<FdLock<F, B>, Error> {
+        let mut state = lock(&self.slot.state);
+        loop {
+            if let Some(result) = state.result.take() {
+                return result;
+            }
+            state = self
+                .slot
+                .done
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+impl<F: AsFile, B: LockBackend> Future for PendingLock<F, B> {
+    type Output = Result<FdLock<F, B>, Error>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = lock(&self.slot.state);
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+impl<F: AsFile, B: LockBackend> std::ops::Drop for PendingLock<F, B> {
+    fn drop(&mut self) {
+        let result = {
+            let mut state = lock(&self.slot.state);
+            state.cancelled = true;
+            state.result.take()
+        };
+        drop(result);
+    }
+}
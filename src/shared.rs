@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use crate::backend::LockBackend;
+use crate::{AsFile, Error, LockStyle, SharedGuard};
+
+/// A shared lock that can be cloned within the process. Clones refer to the same guard, and the
+/// lock is released when the last one is dropped.
+pub struct SharedFdLock<F: AsFile, B: LockBackend = LockStyle>(Arc<SharedGuard<F, B>>);
+
+impl<F: AsFile> SharedFdLock<F> {
+    /// Locks `f` with the default [`LockStyle`].
+    pub fn lock(f: F, blocking: bool) -> Result<Self, Error> {
+        SharedGuard::lock(f, blocking).map(Self::from)
+    }
+}
+impl<F: AsFile, B: LockBackend> SharedFdLock<F, B> {
+    pub fn lock_with(f: F, backend: B, blocking: bool) -> Result<Self, Error> {
+        SharedGuard::lock_with(f, backend, blocking).map(Self::from)
+    }
+    /// Returns the guard if this is the last clone.
+    pub fn try_unwrap(self) -> Result<SharedGuard<F, B>, Self> {
+        Arc::try_unwrap(self.0).map_err(SharedFdLock)
+    }
+}
+impl<F: AsFile, B: LockBackend> From<SharedGuard<F, B>> for SharedFdLock<F, B> {
+    fn from(guard: SharedGuard<F, B>) -> Self {
+        SharedFdLock(Arc::new(guard))
+    }
+}
+impl<F: AsFile, B: LockBackend> Clone for SharedFdLock<F, B> {
+    fn clone(&self) -> Self {
+        SharedFdLock(self.0.clone())
+    }
+}
+impl<F: AsFile, B: LockBackend> std::ops::Deref for SharedFdLock<F, B> {
+    type Target = F;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
@@ -0,0 +1,25 @@
+use std::io::{Stderr, Stdout};
+
+use crate::{AsFile, Error, FdLock, LockType};
+
+/// Waits for an exclusive lock on a standard stream, so processes sharing it (typically a log
+/// file several jobs are redirected to) can take turns writing. See [`lock_stdout`] and
+/// [`lock_stderr`].
+///
+/// The lock is on whatever the stream points at. That has to be something the platform can
+/// lock: on Windows only a file, not a console or pipe. Everyone writing must take the lock for
+/// it to help, and buffered output should be flushed before the guard is dropped.
+pub fn lock_stdio<S: AsFile>(stream: S) -> Result<FdLock<S>, Error> {
+    FdLock::lock(stream, LockType::Exclusive, true)
+}
+
+/// [`lock_stdio`] on [`std::io::stdout`]. The guard doesn't lock `stdout`'s in-process mutex,
+/// and `Stdout` is line buffered, so write whole lines or flush before releasing.
+pub fn lock_stdout() -> Result<FdLock<Stdout>, Error> {
+    lock_stdio(std::io::stdout())
+}
+
+/// [`lock_stdio`] on [`std::io::stderr`].
+pub fn lock_stderr() -> Result<FdLock<Stderr>, Error> {
+    lock_stdio(std::io::stderr())
+}
@@ -0,0 +1,108 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures_core::Stream;
+
+use crate::backend::LockBackend;
+use crate::future::wake_at;
+use crate::{AsFile, Error, FdLock, LockStyle, LockType, RetryPolicy};
+
+/// What happened on one attempt of an [`AcquireStream`].
+pub enum LockEvent<F: AsFile, B: LockBackend = LockStyle> {
+    /// The first attempt found the lock held by someone else.
+    Contended,
+    /// Retry number `attempt` failed too, `waited` after the first attempt.
+    Retrying { attempt: u32, waited: Duration },
+    /// The lock was acquired. This is the last event.
+    Acquired(FdLock<F, B>),
+}
+
+/// A stream that acquires a lock like [`LockFuture`](crate::LockFuture), yielding a
+/// [`LockEvent`] for every attempt so callers can report progress while they wait. It ends after
+/// [`LockEvent::Acquired`] or an error.
+pub struct AcquireStream<F: AsFile, B: LockBackend = LockStyle> {
+    inner: Option<(F, B, LockType)>,
+    started: Option<Instant>,
+    next_attempt: Option<Instant>,
+    attempt: u32,
+    policy: RetryPolicy,
+}
+impl<F: AsFile, B: LockBackend> AcquireStream<F, B> {
+    /// Waits `initial` after the first failed attempt, doubling the wait after each further
+    /// failure up to `max`.
+    pub fn backoff(self, initial: Duration, max: Duration) -> Self {
+        self.retry_policy(RetryPolicy::Exponential { initial, max })
+    }
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+// Nothing is pinned structurally.
+impl<F: AsFile, B: LockBackend> Unpin for AcquireStream<F, B> {}
+impl<F: AsFile, B: LockBackend> Stream for AcquireStream<F, B> {
+    type Item = Result<LockEvent<F, B>, Error>;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let (f, backend, lock_type) = match this.inner.as_ref() {
+            Some(inner) => inner,
+            None => return Poll::Ready(None),
+        };
+        let now = Instant::now();
+        if let Some(at) = this.next_attempt {
+            if now < at {
+                if let Err(e) = wake_at(at, cx.waker().clone()) {
+                    this.inner = None;
+                    return Poll::Ready(Some(Err(e)));
+                }
+                return Poll::Pending;
+            }
+        }
+        let started = *this.started.get_or_insert(now);
+        match backend.try_acquire(f, lock_type) {
+            Ok(()) => {
+                let (f, backend, lock_type) = this.inner.take().unwrap();
+                Poll::Ready(Some(Ok(LockEvent::Acquired(FdLock::assume_locked(
+                    f, backend, lock_type,
+                )))))
+            }
+            Err(Error::WouldBlock) => {
+                let event = if this.attempt == 0 {
+                    LockEvent::Contended
+                } else {
+                    LockEvent::Retrying {
+                        attempt: this.attempt,
+                        waited: now - started,
+                    }
+                };
+                this.attempt += 1;
+                this.next_attempt = Some(now + this.policy.delay(this.attempt));
+                Poll::Ready(Some(Ok(event)))
+            }
+            Err(e) => {
+                this.inner = None;
+                Poll::Ready(Some(Err(e)))
+            }
+        }
+    }
+}
+
+impl<F: AsFile> FdLock<F> {
+    /// Acquires a lock on `f` with the default [`LockStyle`], reporting each attempt. See
+    /// [`AcquireStream`].
+    pub fn acquire_stream(f: F, lock_type: LockType) -> AcquireStream<F> {
+        Self::acquire_stream_with(f, LockStyle::default(), lock_type)
+    }
+}
+impl<F: AsFile, B: LockBackend> FdLock<F, B> {
+    pub fn acquire_stream_with(f: F, backend: B, lock_type: LockType) -> AcquireStream<F, B> {
+        AcquireStream {
+            inner: Some((f, backend, lock_type)),
+            started: None,
+            next_attempt: None,
+            attempt: 0,
+            policy: RetryPolicy::default(),
+        }
+    }
+}
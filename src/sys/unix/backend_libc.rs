@@ -0,0 +1,119 @@
+use std::io::Error as IOError;
+use std::os::unix::io::RawFd;
+
+use crate::{Error, LockType};
+
+#[cfg(not(target_os = "solaris"))]
+pub fn flock(fd: RawFd, lock_type: Option<&LockType>, blocking: bool) -> Result<(), Error> {
+    let mut operation = match lock_type {
+        Some(LockType::Exclusive) => libc::LOCK_EX,
+        Some(LockType::Shared) => libc::LOCK_SH,
+        None => libc::LOCK_UN,
+    };
+    if !blocking {
+        operation |= libc::LOCK_NB;
+    }
+    if unsafe { libc::flock(fd, operation) } == -1 {
+        return Err(IOError::last_os_error().into());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "solaris")]
+pub fn flock(_fd: RawFd, _lock_type: Option<&LockType>, _blocking: bool) -> Result<(), Error> {
+    Err(Error::Unsupported)
+}
+
+#[cfg(not(any(target_os = "fuchsia", target_os = "redox")))]
+fn record_lock(
+    fd: RawFd,
+    cmd: libc::c_int,
+    lock_type: Option<&LockType>,
+    start: u64,
+    len: u64,
+) -> Result<(), Error> {
+    use std::convert::TryInto;
+
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = match lock_type {
+        Some(LockType::Exclusive) => libc::F_WRLCK,
+        Some(LockType::Shared) => libc::F_RDLCK,
+        None => libc::F_UNLCK,
+    } as libc::c_short;
+    lock.l_whence = libc::SEEK_SET as libc::c_short;
+    lock.l_start = start.try_into().map_err(|_| Error::InvalidOperation)?;
+    lock.l_len = len.try_into().map_err(|_| Error::InvalidOperation)?;
+    if unsafe { libc::fcntl(fd, cmd, &lock) } == -1 {
+        let e = IOError::last_os_error();
+        return Err(match e.raw_os_error() {
+            // POSIX allows either errno for a conflicting lock.
+            Some(libc::EACCES) | Some(libc::EAGAIN) => Error::WouldBlock,
+            _ => e.into(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "fuchsia", target_os = "redox")))]
+pub fn setlk(
+    fd: RawFd,
+    lock_type: Option<&LockType>,
+    start: u64,
+    len: u64,
+    blocking: bool,
+) -> Result<(), Error> {
+    record_lock(
+        fd,
+        if blocking {
+            libc::F_SETLKW
+        } else {
+            libc::F_SETLK
+        },
+        lock_type,
+        start,
+        len,
+    )
+}
+
+#[cfg(any(target_os = "fuchsia", target_os = "redox"))]
+pub fn setlk(
+    _fd: RawFd,
+    _lock_type: Option<&LockType>,
+    _start: u64,
+    _len: u64,
+    _blocking: bool,
+) -> Result<(), Error> {
+    Err(Error::Unsupported)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn ofd_setlk(
+    fd: RawFd,
+    lock_type: Option<&LockType>,
+    start: u64,
+    len: u64,
+    blocking: bool,
+) -> Result<(), Error> {
+    record_lock(
+        fd,
+        if blocking {
+            libc::F_OFD_SETLKW
+        } else {
+            libc::F_OFD_SETLK
+        },
+        lock_type,
+        start,
+        len,
+    )
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn ofd_setlk(
+    _fd: RawFd,
+    _lock_type: Option<&LockType>,
+    _start: u64,
+    _len: u64,
+    _blocking: bool,
+) -> Result<(), Error> {
+    Err(Error::Unsupported)
+}
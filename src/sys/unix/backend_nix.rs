@@ -0,0 +1,121 @@
+use std::os::unix::io::RawFd;
+
+#[cfg(not(target_os = "fuchsia"))]
+use nix::errno::Errno;
+
+use crate::{Error, LockType};
+
+#[cfg(not(target_os = "solaris"))]
+pub fn flock(fd: RawFd, lock_type: Option<&LockType>, blocking: bool) -> Result<(), Error> {
+    use nix::fcntl::FlockArg;
+
+    nix::fcntl::flock(
+        fd,
+        match (lock_type, blocking) {
+            (Some(LockType::Exclusive), true) => FlockArg::LockExclusive,
+            (Some(LockType::Exclusive), false) => FlockArg::LockExclusiveNonblock,
+            (Some(LockType::Shared), true) => FlockArg::LockShared,
+            (Some(LockType::Shared), false) => FlockArg::LockSharedNonblock,
+            (None, true) => FlockArg::Unlock,
+            (None, false) => FlockArg::UnlockNonblock,
+        },
+    )?;
+    Ok(())
+}
+
+#[cfg(target_os = "solaris")]
+pub fn flock(_fd: RawFd, _lock_type: Option<&LockType>, _blocking: bool) -> Result<(), Error> {
+    Err(Error::Unsupported)
+}
+
+#[cfg(not(target_os = "fuchsia"))]
+fn record(lock_type: Option<&LockType>, start: u64, len: u64) -> Result<nix::libc::flock, Error> {
+    use std::convert::TryInto;
+
+    use nix::libc;
+
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = match lock_type {
+        Some(LockType::Exclusive) => libc::F_WRLCK,
+        Some(LockType::Shared) => libc::F_RDLCK,
+        None => libc::F_UNLCK,
+    } as libc::c_short;
+    lock.l_whence = libc::SEEK_SET as libc::c_short;
+    lock.l_start = start.try_into().map_err(|_| Error::InvalidOperation)?;
+    lock.l_len = len.try_into().map_err(|_| Error::InvalidOperation)?;
+    Ok(lock)
+}
+
+#[cfg(not(target_os = "fuchsia"))]
+fn record_lock(fd: RawFd, arg: nix::fcntl::FcntlArg) -> Result<(), Error> {
+    match nix::fcntl::fcntl(fd, arg) {
+        Ok(_) => Ok(()),
+        // POSIX allows either errno for a conflicting lock.
+        Err(Errno::EACCES) | Err(Errno::EAGAIN) => Err(Error::WouldBlock),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(not(target_os = "fuchsia"))]
+pub fn setlk(
+    fd: RawFd,
+    lock_type: Option<&LockType>,
+    start: u64,
+    len: u64,
+    blocking: bool,
+) -> Result<(), Error> {
+    use nix::fcntl::FcntlArg;
+
+    let lock = record(lock_type, start, len)?;
+    record_lock(
+        fd,
+        if blocking {
+            FcntlArg::F_SETLKW(&lock)
+        } else {
+            FcntlArg::F_SETLK(&lock)
+        },
+    )
+}
+
+#[cfg(target_os = "fuchsia")]
+pub fn setlk(
+    _fd: RawFd,
+    _lock_type: Option<&LockType>,
+    _start: u64,
+    _len: u64,
+    _blocking: bool,
+) -> Result<(), Error> {
+    Err(Error::Unsupported)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn ofd_setlk(
+    fd: RawFd,
+    lock_type: Option<&LockType>,
+    start: u64,
+    len: u64,
+    blocking: bool,
+) -> Result<(), Error> {
+    use nix::fcntl::FcntlArg;
+
+    let lock = record(lock_type, start, len)?;
+    record_lock(
+        fd,
+        if blocking {
+            FcntlArg::F_OFD_SETLKW(&lock)
+        } else {
+            FcntlArg::F_OFD_SETLK(&lock)
+        },
+    )
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn ofd_setlk(
+    _fd: RawFd,
+    _lock_type: Option<&LockType>,
+    _start: u64,
+    _len: u64,
+    _blocking: bool,
+) -> Result<(), Error> {
+    Err(Error::Unsupported)
+}
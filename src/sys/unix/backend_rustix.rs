@@ -0,0 +1,73 @@
+use std::os::unix::io::{BorrowedFd, RawFd};
+
+use rustix::{fs::FlockOperation, io::Errno};
+
+use crate::{Error, LockType};
+
+fn operation(lock_type: Option<&LockType>, blocking: bool) -> FlockOperation {
+    match (lock_type, blocking) {
+        (Some(LockType::Exclusive), true) => FlockOperation::LockExclusive,
+        (Some(LockType::Exclusive), false) => FlockOperation::NonBlockingLockExclusive,
+        (Some(LockType::Shared), true) => FlockOperation::LockShared,
+        (Some(LockType::Shared), false) => FlockOperation::NonBlockingLockShared,
+        (None, true) => FlockOperation::Unlock,
+        (None, false) => FlockOperation::NonBlockingUnlock,
+    }
+}
+
+#[cfg(not(target_os = "solaris"))]
+pub fn flock(fd: RawFd, lock_type: Option<&LockType>, blocking: bool) -> Result<(), Error> {
+    // The caller guarantees `fd` stays open for the duration of the call.
+    let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+    rustix::fs::flock(fd, operation(lock_type, blocking))?;
+    Ok(())
+}
+
+#[cfg(target_os = "solaris")]
+pub fn flock(_fd: RawFd, _lock_type: Option<&LockType>, _blocking: bool) -> Result<(), Error> {
+    Err(Error::Unsupported)
+}
+
+#[cfg(not(target_os = "fuchsia"))]
+// rustix can only take record locks over the whole file.
+pub fn setlk(
+    fd: RawFd,
+    lock_type: Option<&LockType>,
+    start: u64,
+    len: u64,
+    blocking: bool,
+) -> Result<(), Error> {
+    if start != 0 || len != 0 {
+        return Err(Error::Unsupported);
+    }
+    // The caller guarantees `fd` stays open for the duration of the call.
+    let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+    match rustix::fs::fcntl_lock(fd, operation(lock_type, blocking)) {
+        Ok(()) => Ok(()),
+        // POSIX allows either errno for a conflicting lock.
+        Err(Errno::ACCESS) | Err(Errno::AGAIN) => Err(Error::WouldBlock),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(target_os = "fuchsia")]
+pub fn setlk(
+    _fd: RawFd,
+    _lock_type: Option<&LockType>,
+    _start: u64,
+    _len: u64,
+    _blocking: bool,
+) -> Result<(), Error> {
+    Err(Error::Unsupported)
+}
+
+// rustix has no wrapper for open file description locks.
+pub fn ofd_setlk(
+    _fd: RawFd,
+    _lock_type: Option<&LockType>,
+    _start: u64,
+    _len: u64,
+    _blocking: bool,
+) -> Result<(), Error> {
+    Err(Error::Unsupported)
+}
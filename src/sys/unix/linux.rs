@@ -0,0 +1,335 @@
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use super::{cvt, AsFile, AsRawFile};
+use crate::{Error, LockStyle, LockType};
+
+// Not exported by libc for every Linux target, but the same on all of them.
+const F_SETSIG: libc::c_int = 10;
+
+pub fn set_lease<F: AsFile>(f: &F, lease_type: Option<&LockType>) -> Result<(), Error> {
+    let arg = match lease_type {
+        Some(LockType::Exclusive) => libc::F_WRLCK,
+        Some(LockType::Shared) => libc::F_RDLCK,
+        None => libc::F_UNLCK,
+    };
+    cvt(unsafe { libc::fcntl(f.as_fd().as_raw_fd(), libc::F_SETLEASE, arg) })?;
+    Ok(())
+}
+
+pub fn get_lease<F: AsFile>(f: &F) -> Result<Option<LockType>, Error> {
+    match cvt(unsafe { libc::fcntl(f.as_fd().as_raw_fd(), libc::F_GETLEASE) })? {
+        libc::F_WRLCK => Ok(Some(LockType::Exclusive)),
+        libc::F_RDLCK => Ok(Some(LockType::Shared)),
+        _ => Ok(None),
+    }
+}
+
+pub fn set_lease_signal<F: AsFile>(f: &F, signal: i32) -> Result<(), Error> {
+    cvt(unsafe { libc::fcntl(f.as_fd().as_raw_fd(), F_SETSIG, signal) })?;
+    Ok(())
+}
+
+extern "C" fn interrupt(_: libc::c_int) {}
+
+fn timespec(d: Duration) -> libc::timespec {
+    libc::timespec {
+        tv_sec: d.as_secs() as libc::time_t,
+        tv_nsec: d.subsec_nanos() as libc::c_long,
+    }
+}
+
+// Which real-time signal interrupts the wait, as an offset from SIGRTMIN, and how installing
+// its handler went the first time one was needed.
+struct Interrupt {
+    offset: libc::c_int,
+    installed: Option<Installed>,
+}
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Installed {
+    Ours(libc::c_int),
+    // Something else had a handler there (or ignores it), which is left alone.
+    Taken,
+    Failed(libc::c_int),
+}
+static INTERRUPT: Mutex<Interrupt> = Mutex::new(Interrupt {
+    offset: 8,
+    installed: None,
+});
+
+fn interrupt_state() -> MutexGuard<'static, Interrupt> {
+    INTERRUPT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn set_lock_timeout_signal(offset: i32) -> Result<(), Error> {
+    if offset < 0 || libc::SIGRTMIN() + offset > libc::SIGRTMAX() {
+        return Err(Error::InvalidOperation);
+    }
+    let mut state = interrupt_state();
+    match state.installed {
+        Some(Installed::Ours(_)) if state.offset != offset => Err(Error::InvalidOperation),
+        Some(Installed::Ours(_)) => Ok(()),
+        // A signal that couldn't be used can still be traded for another.
+        _ => {
+            state.offset = offset;
+            state.installed = None;
+            Ok(())
+        }
+    }
+}
+
+// The handler is installed once and kept: restoring the old disposition per call would race
+// with other threads' timers still being armed. It only goes where nothing was installed
+// before, so an application's own handler for the signal is never replaced.
+fn install_interrupt() -> Result<libc::c_int, Error> {
+    let mut state = interrupt_state();
+    let signal = libc::SIGRTMIN() + state.offset;
+    let installed = *state.installed.get_or_insert_with(|| unsafe {
+        let mut old: libc::sigaction = std::mem::zeroed();
+        if libc::sigaction(signal, std::ptr::null(), &mut old) == -1 {
+            return Installed::Failed(last_errno());
+        }
+        if old.sa_sigaction != libc::SIG_DFL {
+            return Installed::Taken;
+        }
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        match libc::sigaction(signal, &action, std::ptr::null_mut()) {
+            -1 => Installed::Failed(last_errno()),
+            _ => Installed::Ours(signal),
+        }
+    });
+    match installed {
+        Installed::Ours(signal) => Ok(signal),
+        Installed::Taken => Err(Error::Unsupported),
+        Installed::Failed(errno) => Err(super::io_error(std::io::Error::from_raw_os_error(errno))),
+    }
+}
+
+fn last_errno() -> libc::c_int {
+    std::io::Error::last_os_error()
+        .raw_os_error()
+        .unwrap_or(libc::EINVAL)
+}
+
+// Waits in a blocking lock call, with a per-thread timer sending a real-time signal to
+// interrupt it once `timeout` has passed. The timer keeps firing every millisecond after that,
+// in case the first signal lands just before the call starts waiting. Fails with
+// `Error::Unsupported`, before trying the lock, if the signal already had another handler.
+pub fn lock_timeout_signal<F: AsFile>(
+    f: &F,
+    style: &LockStyle,
+    lock_type: &LockType,
+    timeout: Duration,
+) -> Result<(), Error> {
+    let deadline = Instant::now() + timeout;
+    let signal = install_interrupt()?;
+    unsafe {
+        let mut event: libc::sigevent = std::mem::zeroed();
+        event.sigev_notify = libc::SIGEV_THREAD_ID;
+        event.sigev_signo = signal;
+        event.sigev_notify_thread_id = libc::gettid();
+        let mut timer: libc::timer_t = std::mem::zeroed();
+        cvt(libc::timer_create(
+            libc::CLOCK_MONOTONIC,
+            &mut event,
+            &mut timer,
+        ))?;
+        let spec = libc::itimerspec {
+            it_interval: timespec(Duration::from_millis(1)),
+            it_value: timespec(std::cmp::max(timeout, Duration::from_nanos(1))),
+        };
+        let res = match cvt(libc::timer_settime(timer, 0, &spec, std::ptr::null_mut())) {
+            Err(e) => Err(e),
+            Ok(_) => loop {
+                match super::lock(f, style, lock_type, true) {
+                    // Some other signal.
+                    Err(Error::Interrupted) if Instant::now() < deadline => continue,
+                    Err(Error::Interrupted) => break Err(Error::Timeout),
+                    res => break res,
+                }
+            },
+        };
+        libc::timer_delete(timer);
+        res
+    }
+}
+
+// Reads the kernel's own bookkeeping rather than probing, so nobody else's attempts are
+// disturbed. flock(2) and OFD locks belong to an open file description, and /proc/self/fdinfo
+// lists exactly the ones on `f`'s. fcntl(2) locks belong to the process, so /proc/locks is
+// searched for one with this pid on the same file; that one lists every holder, where
+// F_GETLK would only report the first conflict.
+pub fn is_held<F: AsFile>(f: &F, style: &LockStyle) -> Result<bool, Error> {
+    let fd = f.as_fd().as_raw_fd();
+    let file = proc_id(f)?;
+    match style {
+        LockStyle::Flock => listed(&format!("/proc/self/fdinfo/{}", fd), "FLOCK", file, None),
+        LockStyle::Ofd => listed(&format!("/proc/self/fdinfo/{}", fd), "OFDLCK", file, None),
+        LockStyle::Fcntl => listed(
+            "/proc/locks",
+            "POSIX",
+            file,
+            Some(std::process::id().to_string()),
+        ),
+    }
+}
+
+// How /proc/locks names `f`'s file: (device major, device minor, inode).
+fn proc_id<F: AsFile>(f: &F) -> Result<(u64, u64, u64), Error> {
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    cvt(unsafe { libc::fstat(f.as_fd().as_raw_fd(), &mut stat) })?;
+    #[allow(clippy::unnecessary_cast)]
+    Ok((
+        libc::major(stat.st_dev) as u64,
+        libc::minor(stat.st_dev) as u64,
+        stat.st_ino as u64,
+    ))
+}
+
+// Whether `table` (in the format of /proc/locks, or the `lock:` lines of an fdinfo file) has a
+// granted lock of `kind` on `file` (major, minor, inode), held by `pid` if given.
+fn listed(
+    table: &str,
+    kind: &str,
+    file: (u64, u64, u64),
+    pid: Option<String>,
+) -> Result<bool, Error> {
+    let contents = match std::fs::read_to_string(table) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(Error::Unsupported),
+        Err(e) => return Err(super::io_error(e)),
+    };
+    Ok(contents.lines().any(|line| {
+        // "1: POSIX  ADVISORY  WRITE 1234 fe:00:5678 0 EOF"; waiters have "->" after the id.
+        let mut fields = line
+            .strip_prefix("lock:")
+            .unwrap_or(line)
+            .split_whitespace();
+        let _id = fields.next();
+        if fields.next() != Some(kind) {
+            return false;
+        }
+        let (_, _, holder, id) = (fields.next(), fields.next(), fields.next(), fields.next());
+        let mut id = id.unwrap_or("").split(':');
+        let id = (
+            id.next().and_then(|n| u64::from_str_radix(n, 16).ok()),
+            id.next().and_then(|n| u64::from_str_radix(n, 16).ok()),
+            id.next().and_then(|n| n.parse().ok()),
+        );
+        id == (Some(file.0), Some(file.1), Some(file.2))
+            && pid.as_deref().is_none_or(|pid| holder == Some(pid))
+    }))
+}
+
+// A fresh descriptor's OFD lock request conflicts with every lock on the file, including this
+// process's own fcntl(2) ones. flock(2) locks can't be asked about, but show up in /proc/locks.
+pub fn query<F: AsFile>(f: &F, style: &LockStyle) -> Result<bool, Error> {
+    match style {
+        LockStyle::Flock => listed("/proc/locks", "FLOCK", proc_id(f)?, None),
+        LockStyle::Fcntl | LockStyle::Ofd => super::conflicting(f, libc::F_OFD_GETLK),
+    }
+}
+
+const CHANGED: u32 =
+    libc::IN_MODIFY | libc::IN_CREATE | libc::IN_DELETE | libc::IN_MOVED_FROM | libc::IN_MOVED_TO;
+// Worth looking again: an mtime set by hand, or the last descriptor closing (and so its locks
+// going).
+const TOUCHED: u32 = libc::IN_ATTRIB;
+const CLOSED: u32 = libc::IN_CLOSE_WRITE | libc::IN_CLOSE_NOWRITE;
+
+// inotify on the parent directory rather than the file itself, so that replacing the file by
+// rename, or creating it, is seen too.
+pub struct Watch {
+    fd: std::os::unix::io::OwnedFd,
+    name: std::ffi::OsString,
+}
+
+pub fn watch(path: &std::path::Path, closes: bool) -> Result<Watch, Error> {
+    use std::os::unix::io::{FromRawFd, OwnedFd};
+
+    let name = path.file_name().ok_or(Error::InvalidOperation)?.to_owned();
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => std::path::Path::new("."),
+    };
+    let dir = super::c_path(dir)?;
+    let fd = cvt(unsafe { libc::inotify_init1(libc::IN_CLOEXEC | libc::IN_NONBLOCK) })?;
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+    let mask = if closes {
+        CHANGED | TOUCHED | CLOSED
+    } else {
+        CHANGED | TOUCHED
+    };
+    cvt(unsafe { libc::inotify_add_watch(fd.as_raw_fd(), dir.as_ptr(), mask) })?;
+    Ok(Watch { fd, name })
+}
+
+impl Watch {
+    // Waits up to `timeout` for an event on the file. Returns whether the file was changed,
+    // rather than just closed, or nothing happened in time.
+    pub fn wait(&self, timeout: Duration) -> Result<bool, Error> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(changed) = self.read()? {
+                return Ok(changed);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(false);
+            }
+            let mut pollfd = libc::pollfd {
+                fd: self.fd.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let ms = remaining
+                .as_micros()
+                .div_ceil(1000)
+                .min(libc::c_int::MAX as u128);
+            match cvt(unsafe { libc::poll(&mut pollfd, 1, ms as libc::c_int) }) {
+                Ok(_) | Err(Error::Interrupted) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // Drains the queue: `Some(true)` if any event was a change to the file, `Some(false)` if
+    // there were only others (or the queue overflowed), `None` if it was empty.
+    fn read(&self) -> Result<Option<bool>, Error> {
+        const HEADER: usize = std::mem::size_of::<libc::inotify_event>();
+        let mut seen = None;
+        loop {
+            let mut buf = [0u64; 512];
+            let n = unsafe {
+                libc::read(
+                    self.fd.as_raw_fd(),
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    std::mem::size_of_val(&buf),
+                )
+            };
+            if n < 0 {
+                match std::io::Error::last_os_error().kind() {
+                    std::io::ErrorKind::WouldBlock => return Ok(seen),
+                    std::io::ErrorKind::Interrupted => continue,
+                    _ => return Err(super::io_error(std::io::Error::last_os_error())),
+                }
+            }
+            let bytes =
+                unsafe { std::slice::from_raw_parts(buf.as_ptr() as *const u8, n as usize) };
+            let mut offset = 0;
+            while offset + HEADER <= bytes.len() {
+                let event: libc::inotify_event =
+                    unsafe { std::ptr::read_unaligned(bytes[offset..].as_ptr() as *const _) };
+                let name = &bytes[offset + HEADER..offset + HEADER + event.len as usize];
+                let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+                offset += HEADER + event.len as usize;
+                if event.mask & libc::IN_Q_OVERFLOW != 0 {
+                    seen = Some(seen.unwrap_or(false));
+                } else if std::os::unix::ffi::OsStrExt::as_bytes(self.name.as_os_str()) == name {
+                    seen = Some(seen.unwrap_or(false) || event.mask & CHANGED != 0);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,613 @@
+pub use std::os::unix::io::{
+    AsFd as AsFile, AsRawFd as AsRawFile, FromRawFd as FromRawFile, IntoRawFd as IntoRawFile,
+    RawFd as RawDescriptor,
+};
+
+use std::ffi::CString;
+use std::fs::File;
+use std::io::Error as IOError;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::RawFd;
+use std::path::{Path, PathBuf};
+
+use crate::backend::Filesystem;
+use crate::{Error, LockStyle, LockType};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod linux;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use self::linux::*;
+
+// The opt-in backends take precedence over the default nix one when several are enabled. nix
+// does not build against relibc, so Redox always talks to libc directly.
+#[cfg(any(feature = "backend-libc", target_os = "redox"))]
+mod backend_libc;
+#[cfg(any(feature = "backend-libc", target_os = "redox"))]
+use self::backend_libc as backend;
+
+#[cfg(all(
+    feature = "backend-rustix",
+    not(any(feature = "backend-libc", target_os = "redox"))
+))]
+mod backend_rustix;
+#[cfg(all(
+    feature = "backend-rustix",
+    not(any(feature = "backend-libc", target_os = "redox"))
+))]
+use self::backend_rustix as backend;
+
+#[cfg(all(
+    feature = "backend-nix",
+    not(any(
+        feature = "backend-libc",
+        feature = "backend-rustix",
+        target_os = "redox"
+    ))
+))]
+mod backend_nix;
+#[cfg(all(
+    feature = "backend-nix",
+    not(any(
+        feature = "backend-libc",
+        feature = "backend-rustix",
+        target_os = "redox"
+    ))
+))]
+use self::backend_nix as backend;
+
+#[cfg(not(any(
+    feature = "backend-nix",
+    feature = "backend-rustix",
+    feature = "backend-libc",
+    target_os = "redox"
+)))]
+compile_error!(
+    "fd-lock-rs needs one of the `backend-nix`, `backend-rustix` or `backend-libc` features on unix"
+);
+
+// flock(2) is emulated on top of fcntl(2) on these platforms (or missing entirely), so use
+// record locks over the whole file directly.
+pub const DEFAULT_STYLE: LockStyle = if cfg!(any(
+    target_os = "solaris",
+    target_os = "illumos",
+    target_os = "aix"
+)) {
+    LockStyle::Fcntl
+} else {
+    LockStyle::Flock
+};
+
+pub fn lock<F: AsFile>(
+    f: &F,
+    style: &LockStyle,
+    lock_type: &LockType,
+    blocking: bool,
+) -> Result<(), Error> {
+    lock_op(f, style, Some(lock_type), 0, 0, blocking)
+}
+
+pub fn unlock<F: AsFile>(f: &F, style: &LockStyle, blocking: bool) -> Result<(), Error> {
+    lock_op(f, style, None, 0, 0, blocking)
+}
+
+// Both flock(2) and fcntl(2) convert a held lock when it is requested again with another type.
+// flock(2) drops the old lock before trying the new type, so a downgrade would let a writer in
+// and isn't offered, and a failed upgrade has to take the old lock back, waiting only if the
+// caller was willing to.
+pub fn relock<F: AsFile>(
+    f: &F,
+    style: &LockStyle,
+    from: &LockType,
+    to: &LockType,
+    blocking: bool,
+) -> Result<(), Error> {
+    if *style == LockStyle::Flock && *to == LockType::Shared {
+        return Err(Error::Unsupported);
+    }
+    match lock(f, style, to, blocking) {
+        Err(e) if *style == LockStyle::Flock => {
+            lock(f, style, from, blocking)?;
+            Err(e)
+        }
+        res => res,
+    }
+}
+
+pub fn lock_range<F: AsFile>(
+    f: &F,
+    style: &LockStyle,
+    lock_type: &LockType,
+    start: u64,
+    len: u64,
+    blocking: bool,
+) -> Result<(), Error> {
+    if *style == LockStyle::Flock {
+        return Err(Error::InvalidOperation);
+    }
+    lock_op(f, style, Some(lock_type), start, len, blocking)
+}
+
+pub fn unlock_range<F: AsFile>(
+    f: &F,
+    style: &LockStyle,
+    start: u64,
+    len: u64,
+    blocking: bool,
+) -> Result<(), Error> {
+    if *style == LockStyle::Flock {
+        return Err(Error::InvalidOperation);
+    }
+    lock_op(f, style, None, start, len, blocking)
+}
+
+// A `len` of 0 covers everything from `start` to the end of the file, however far it grows.
+fn lock_op<F: AsFile>(
+    f: &F,
+    style: &LockStyle,
+    lock_type: Option<&LockType>,
+    start: u64,
+    len: u64,
+    blocking: bool,
+) -> Result<(), Error> {
+    let fd = f.as_fd().as_raw_fd();
+    match style {
+        LockStyle::Flock => backend::flock(fd, lock_type, blocking),
+        LockStyle::Fcntl => backend::setlk(fd, lock_type, start, len, blocking),
+        LockStyle::Ofd => backend::ofd_setlk(fd, lock_type, start, len, blocking),
+    }
+}
+
+// Passes `operation` to flock(2) as is, returning which kind of lock it takes.
+#[cfg(not(target_os = "solaris"))]
+pub fn flock_raw<F: AsFile>(f: &F, operation: libc::c_int) -> Result<LockType, Error> {
+    let lock_type = if operation & libc::LOCK_SH != 0 {
+        LockType::Shared
+    } else if operation & libc::LOCK_EX != 0 {
+        LockType::Exclusive
+    } else {
+        return Err(Error::InvalidOperation);
+    };
+    cvt(unsafe { libc::flock(f.as_fd().as_raw_fd(), operation) })?;
+    Ok(lock_type)
+}
+
+#[cfg(target_os = "solaris")]
+pub fn flock_raw<F: AsFile>(_f: &F, _operation: libc::c_int) -> Result<LockType, Error> {
+    Err(Error::Unsupported)
+}
+
+pub fn open_locked(
+    path: &Path,
+    style: &LockStyle,
+    lock_type: &LockType,
+    blocking: bool,
+) -> Result<File, Error> {
+    open_locked_at(libc::AT_FDCWD, path, style, lock_type, blocking)
+}
+
+pub fn open_locked_at(
+    dir: RawFd,
+    path: &Path,
+    style: &LockStyle,
+    lock_type: &LockType,
+    blocking: bool,
+) -> Result<File, Error> {
+    let path = c_path(path)?;
+    let atomic = open_lock_flags(style, lock_type, blocking);
+    let flags = libc::O_RDWR | libc::O_CREAT | libc::O_CLOEXEC | atomic.unwrap_or(0);
+    let fd = cvt(unsafe { libc::openat(dir, path.as_ptr(), flags, 0o666 as libc::c_uint) })?;
+    let f = unsafe { File::from_raw_fd(fd) };
+    match atomic {
+        Some(_) if !blocking => {
+            // O_NONBLOCK only needed to apply to taking the lock.
+            let fl = cvt(unsafe { libc::fcntl(fd, libc::F_GETFL) })?;
+            cvt(unsafe { libc::fcntl(fd, libc::F_SETFL, fl & !libc::O_NONBLOCK) })?;
+        }
+        Some(_) => (),
+        None => lock(&f, style, lock_type, blocking)?,
+    }
+    Ok(f)
+}
+
+pub fn open_at(dir: RawFd, path: &Path, flags: libc::c_int) -> Result<File, Error> {
+    let path = c_path(path)?;
+    let flags = flags | libc::O_CLOEXEC;
+    let fd = cvt(unsafe { libc::openat(dir, path.as_ptr(), flags, 0o666 as libc::c_uint) })?;
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+pub fn open_dir(path: &Path) -> Result<File, Error> {
+    open_at(libc::AT_FDCWD, path, libc::O_RDONLY | libc::O_DIRECTORY)
+}
+
+pub fn unlink_at(dir: RawFd, path: &Path) -> Result<(), Error> {
+    let path = c_path(path)?;
+    cvt(unsafe { libc::unlinkat(dir, path.as_ptr(), 0) })?;
+    Ok(())
+}
+
+// The BSDs can take the flock(2) lock as part of open(2), so no other process ever sees the
+// file open but unlocked.
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+fn open_lock_flags(style: &LockStyle, lock_type: &LockType, blocking: bool) -> Option<libc::c_int> {
+    if *style != LockStyle::Flock {
+        return None;
+    }
+    let flags = match lock_type {
+        LockType::Exclusive => libc::O_EXLOCK,
+        LockType::Shared => libc::O_SHLOCK,
+    };
+    Some(if blocking {
+        flags
+    } else {
+        flags | libc::O_NONBLOCK
+    })
+}
+
+#[cfg(not(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd"
+)))]
+fn open_lock_flags(
+    _style: &LockStyle,
+    _lock_type: &LockType,
+    _blocking: bool,
+) -> Option<libc::c_int> {
+    None
+}
+
+// The errno each variant is mapped from. The rest are produced by this crate itself.
+pub fn raw_os_error(e: &Error) -> Option<i32> {
+    match e {
+        Error::InvalidFd => Some(libc::EBADF),
+        Error::Interrupted => Some(libc::EINTR),
+        Error::InvalidOperation => Some(libc::EINVAL),
+        Error::OutOfMemory => Some(libc::ENOLCK),
+        Error::WouldBlock => Some(libc::EWOULDBLOCK),
+        Error::Deadlock => Some(libc::EDEADLK),
+        Error::Other(e) => e.raw_os_error(),
+        _ => None,
+    }
+}
+
+// Every backend hands its errors over as `std::io::Error`s, so which one is in use makes no
+// difference to `Error::Other`.
+impl From<IOError> for Error {
+    fn from(e: IOError) -> Self {
+        match e.raw_os_error() {
+            Some(libc::EBADF) => Error::InvalidFd,
+            Some(libc::EINTR) => Error::Interrupted,
+            Some(libc::EINVAL) => Error::InvalidOperation,
+            Some(libc::ENOLCK) => Error::OutOfMemory,
+            Some(libc::EWOULDBLOCK) => Error::WouldBlock,
+            Some(libc::EDEADLK) => Error::Deadlock,
+            // fdio only implements flock(2) on top of fuchsia.io advisory locking, which not
+            // every filesystem (or remote directory proxy) provides.
+            #[cfg(target_os = "fuchsia")]
+            Some(libc::ENOTSUP) | Some(libc::ENOSYS) => Error::Unsupported,
+            #[cfg(target_os = "redox")]
+            Some(libc::ENOSYS) => Error::Unsupported,
+            _ => Error::Other(e),
+        }
+    }
+}
+
+// The conversions to and from the errno types of nix and rustix come with those dependencies,
+// whichever backend is doing the locking.
+#[cfg(all(feature = "backend-nix", not(target_os = "redox")))]
+impl From<nix::errno::Errno> for Error {
+    fn from(e: nix::errno::Errno) -> Self {
+        IOError::from_raw_os_error(e as i32).into()
+    }
+}
+
+/// Errors without an errno of their own become the closest match: `ETIMEDOUT` for
+/// [`Error::Timeout`], `ENOTSUP` for [`Error::Unsupported`], and `EINVAL` otherwise.
+#[cfg(all(feature = "backend-nix", not(target_os = "redox")))]
+impl From<Error> for nix::errno::Errno {
+    fn from(e: Error) -> Self {
+        nix::errno::Errno::from_i32(errno(&e))
+    }
+}
+
+#[cfg(all(feature = "backend-rustix", not(target_os = "redox")))]
+impl From<rustix::io::Errno> for Error {
+    fn from(e: rustix::io::Errno) -> Self {
+        IOError::from_raw_os_error(e.raw_os_error()).into()
+    }
+}
+
+/// Errors without an errno of their own become the closest match: `ETIMEDOUT` for
+/// [`Error::Timeout`], `ENOTSUP` for [`Error::Unsupported`], and `EINVAL` otherwise.
+#[cfg(all(feature = "backend-rustix", not(target_os = "redox")))]
+impl From<Error> for rustix::io::Errno {
+    fn from(e: Error) -> Self {
+        rustix::io::Errno::from_raw_os_error(errno(&e))
+    }
+}
+
+// Like `raw_os_error`, with the closest errno for the errors that don't have one.
+#[cfg(all(
+    any(feature = "backend-nix", feature = "backend-rustix"),
+    not(target_os = "redox")
+))]
+fn errno(e: &Error) -> i32 {
+    raw_os_error(e).unwrap_or(match e {
+        Error::Timeout => libc::ETIMEDOUT,
+        Error::Unsupported => libc::ENOTSUP,
+        _ => libc::EINVAL,
+    })
+}
+
+pub fn io_error(e: IOError) -> Error {
+    e.into()
+}
+
+// Everything beyond the core locking calls goes straight to libc.
+fn cvt(ret: libc::c_int) -> Result<libc::c_int, Error> {
+    if ret == -1 {
+        return Err(io_error(IOError::last_os_error()));
+    }
+    Ok(ret)
+}
+
+pub fn hostname() -> String {
+    let mut buf = [0u8; 256];
+    if unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) } == -1 {
+        return String::from("localhost");
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+pub fn link_count(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    std::fs::metadata(path).ok().map(|m| m.nlink())
+}
+
+// The runtime dir is private to the user; the shared temp dir needs the uid in the name.
+pub fn user_lock_path(name: &str) -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => Path::new(&dir).join(format!("{}.lock", name)),
+        _ => std::env::temp_dir().join(format!("{}-{}.lock", name, unsafe { libc::getuid() })),
+    }
+}
+
+pub fn system_lock_path(name: &str) -> PathBuf {
+    let dir = ["/run/lock", "/var/lock", "/var/run"]
+        .iter()
+        .map(Path::new)
+        .find(|dir| dir.is_dir())
+        .map_or_else(std::env::temp_dir, Path::to_owned);
+    dir.join(format!("{}.lock", name))
+}
+
+pub fn process_alive(pid: u32) -> Result<bool, Error> {
+    // kill(2) takes 0 and negative pids to mean process groups.
+    let pid = match <libc::pid_t as std::convert::TryFrom<u32>>::try_from(pid) {
+        Ok(pid) if pid > 0 => pid,
+        _ => return Err(Error::InvalidOperation),
+    };
+    if unsafe { libc::kill(pid, 0) } == -1 {
+        let e = IOError::last_os_error();
+        return match e.raw_os_error() {
+            // It exists, it just isn't ours to signal.
+            Some(libc::EPERM) => Ok(true),
+            Some(libc::ESRCH) => Ok(false),
+            _ => Err(io_error(e)),
+        };
+    }
+    Ok(!is_zombie(pid))
+}
+
+// A zombie still answers kill(2), but it has exited and released its locks.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn is_zombie(pid: libc::pid_t) -> bool {
+    std::fs::read_to_string(format!("/proc/{}/stat", pid))
+        .ok()
+        .and_then(|stat| {
+            let state = stat[stat.rfind(')')? + 1..].trim_start();
+            Some(state.starts_with('Z'))
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn is_zombie(_pid: libc::pid_t) -> bool {
+    false
+}
+
+pub fn set_cloexec<F: AsFile>(f: &F, cloexec: bool) -> Result<(), Error> {
+    let fd = f.as_fd().as_raw_fd();
+    let flags = cvt(unsafe { libc::fcntl(fd, libc::F_GETFD) })?;
+    let flags = if cloexec {
+        flags | libc::FD_CLOEXEC
+    } else {
+        flags & !libc::FD_CLOEXEC
+    };
+    cvt(unsafe { libc::fcntl(fd, libc::F_SETFD, flags) })?;
+    Ok(())
+}
+
+pub fn as_raw<F: AsFile>(f: &F) -> RawDescriptor {
+    f.as_fd().as_raw_fd()
+}
+
+pub fn into_raw<F: IntoRawFile>(f: F) -> RawDescriptor {
+    f.into_raw_fd()
+}
+
+pub unsafe fn from_raw<F: FromRawFile>(raw: RawDescriptor) -> F {
+    F::from_raw_fd(raw)
+}
+
+// (st_dev, st_ino): identifies the file itself, whichever path or descriptor it was opened by.
+pub fn file_id<F: AsFile>(f: &F) -> Result<(u64, u64), Error> {
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    cvt(unsafe { libc::fstat(f.as_fd().as_raw_fd(), &mut stat) })?;
+    #[allow(clippy::unnecessary_cast)]
+    Ok((stat.st_dev as u64, stat.st_ino as u64))
+}
+
+// The id of the file at `path`, without opening it: closing any descriptor on a file drops the
+// process's fcntl(2) locks on it.
+pub fn path_id(path: &Path) -> Result<Option<(u64, u64)>, Error> {
+    use std::os::unix::fs::MetadataExt;
+    match std::fs::metadata(path) {
+        Ok(m) => Ok(Some((m.dev(), m.ino()))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(io_error(e)),
+    }
+}
+
+fn c_path(path: &Path) -> Result<CString, Error> {
+    CString::new(path.as_os_str().as_bytes()).map_err(|_| Error::InvalidOperation)
+}
+
+// Whether the file has no names left, i.e. was removed while open.
+#[cfg(debug_assertions)]
+pub fn unlinked<F: AsFile>(f: &F) -> bool {
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    cvt(unsafe { libc::fstat(f.as_fd().as_raw_fd(), &mut stat) }).is_ok() && stat.st_nlink == 0
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn is_held<F: AsFile>(_f: &F, _style: &LockStyle) -> Result<bool, Error> {
+    Err(Error::Unsupported)
+}
+
+// F_GETLK only reports other processes' record locks: this process's own can never conflict
+// with its own request.
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "fuchsia",
+    target_os = "redox"
+)))]
+pub fn query<F: AsFile>(f: &F, style: &LockStyle) -> Result<bool, Error> {
+    if *style != LockStyle::Fcntl {
+        return Err(Error::Unsupported);
+    }
+    conflicting(f, libc::F_GETLK)
+}
+
+#[cfg(any(target_os = "fuchsia", target_os = "redox"))]
+pub fn query<F: AsFile>(_f: &F, _style: &LockStyle) -> Result<bool, Error> {
+    Err(Error::Unsupported)
+}
+
+// Whether a whole-file write lock requested on `f` with `cmd` (a GETLK variant) would conflict
+// with a lock someone else holds.
+#[cfg(not(any(target_os = "fuchsia", target_os = "redox")))]
+fn conflicting<F: AsFile>(f: &F, cmd: libc::c_int) -> Result<bool, Error> {
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = libc::F_WRLCK as libc::c_short;
+    lock.l_whence = libc::SEEK_SET as libc::c_short;
+    cvt(unsafe { libc::fcntl(f.as_fd().as_raw_fd(), cmd, &mut lock) })?;
+    Ok(lock.l_type != libc::F_UNLCK as libc::c_short)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn filesystem(path: &Path) -> Result<Filesystem, Error> {
+    const NFS_SUPER_MAGIC: u32 = 0x6969;
+    const SMB_SUPER_MAGIC: u32 = 0x517b;
+    const CIFS_SUPER_MAGIC: u32 = 0xff53_4d42;
+    const SMB2_SUPER_MAGIC: u32 = 0xfe53_4d42;
+
+    let path = c_path(path)?;
+    let mut buf: libc::statfs = unsafe { std::mem::zeroed() };
+    cvt(unsafe { libc::statfs(path.as_ptr(), &mut buf) })?;
+    // f_type is a different integer type on nearly every architecture, but the magic numbers
+    // all fit in 32 bits.
+    Ok(match buf.f_type as u32 {
+        NFS_SUPER_MAGIC => Filesystem::Nfs,
+        SMB_SUPER_MAGIC | CIFS_SUPER_MAGIC | SMB2_SUPER_MAGIC => Filesystem::Smb,
+        _ => Filesystem::Other,
+    })
+}
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd"
+))]
+pub fn filesystem(path: &Path) -> Result<Filesystem, Error> {
+    use std::ffi::CStr;
+
+    let path = c_path(path)?;
+    let mut buf: libc::statfs = unsafe { std::mem::zeroed() };
+    cvt(unsafe { libc::statfs(path.as_ptr(), &mut buf) })?;
+    let name = unsafe { CStr::from_ptr(buf.f_fstypename.as_ptr()) };
+    Ok(match name.to_bytes() {
+        b"nfs" => Filesystem::Nfs,
+        b"smbfs" | b"cifs" => Filesystem::Smb,
+        _ => Filesystem::Other,
+    })
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd"
+)))]
+pub fn filesystem(_path: &Path) -> Result<Filesystem, Error> {
+    Ok(Filesystem::Other)
+}
+
+// System V mandatory locking is enabled per file by setting the setgid bit without group
+// execute permission. Linux additionally requires the filesystem to be mounted with `-o mand`,
+// which kernels since 5.15 no longer honor.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "solaris",
+    target_os = "illumos"
+))]
+pub fn check_mandatory<F: AsFile>(f: &F) -> Result<(), Error> {
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    cvt(unsafe { libc::fstat(f.as_fd().as_raw_fd(), &mut stat) })?;
+    if stat.st_mode & libc::S_ISGID == 0 || stat.st_mode & libc::S_IXGRP != 0 {
+        return Err(Error::Misconfigured(
+            "mandatory locking needs the setgid bit set and group execute cleared",
+        ));
+    }
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let mut statvfs: libc::statvfs = unsafe { std::mem::zeroed() };
+        cvt(unsafe { libc::fstatvfs(f.as_fd().as_raw_fd(), &mut statvfs) })?;
+        if statvfs.f_flag & libc::ST_MANDLOCK == 0 {
+            return Err(Error::Misconfigured(
+                "the filesystem is not mounted with mandatory locking (-o mand)",
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "solaris",
+    target_os = "illumos"
+)))]
+pub fn check_mandatory<F: AsFile>(_f: &F) -> Result<(), Error> {
+    Err(Error::Unsupported)
+}
@@ -0,0 +1,159 @@
+pub use std::os::wasi::io::{
+    AsFd as AsFile, AsRawFd as AsRawFile, FromRawFd as FromRawFile, IntoRawFd as IntoRawFile,
+    RawFd as RawDescriptor,
+};
+
+use std::fs::{File, OpenOptions};
+use std::io::Error as IOError;
+use std::path::{Path, PathBuf};
+
+use crate::backend::Filesystem;
+use crate::{Error, LockStyle, LockType};
+
+pub const DEFAULT_STYLE: LockStyle = LockStyle::Flock;
+
+impl From<IOError> for Error {
+    fn from(e: IOError) -> Self {
+        Error::Other(e)
+    }
+}
+
+pub fn raw_os_error(e: &Error) -> Option<i32> {
+    match e {
+        Error::Other(e) => e.raw_os_error(),
+        _ => None,
+    }
+}
+
+// WASI preview 1 has no advisory locking primitive, so every request is reported as
+// unsupported rather than silently pretending the lock was taken.
+pub fn lock<F: AsFile>(
+    _f: &F,
+    _style: &LockStyle,
+    _lock_type: &LockType,
+    _blocking: bool,
+) -> Result<(), Error> {
+    Err(Error::Unsupported)
+}
+
+pub fn unlock<F: AsFile>(_f: &F, _style: &LockStyle, _blocking: bool) -> Result<(), Error> {
+    Err(Error::Unsupported)
+}
+
+pub fn relock<F: AsFile>(
+    _f: &F,
+    _style: &LockStyle,
+    _from: &LockType,
+    _to: &LockType,
+    _blocking: bool,
+) -> Result<(), Error> {
+    Err(Error::Unsupported)
+}
+
+pub fn lock_range<F: AsFile>(
+    _f: &F,
+    _style: &LockStyle,
+    _lock_type: &LockType,
+    _start: u64,
+    _len: u64,
+    _blocking: bool,
+) -> Result<(), Error> {
+    Err(Error::Unsupported)
+}
+
+pub fn unlock_range<F: AsFile>(
+    _f: &F,
+    _style: &LockStyle,
+    _start: u64,
+    _len: u64,
+    _blocking: bool,
+) -> Result<(), Error> {
+    Err(Error::Unsupported)
+}
+
+pub fn open_locked(
+    path: &Path,
+    style: &LockStyle,
+    lock_type: &LockType,
+    blocking: bool,
+) -> Result<File, Error> {
+    let f = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    lock(&f, style, lock_type, blocking)?;
+    Ok(f)
+}
+
+pub fn io_error(e: IOError) -> Error {
+    e.into()
+}
+
+pub fn hostname() -> String {
+    String::from("localhost")
+}
+
+pub fn user_lock_path(name: &str) -> PathBuf {
+    // There's no temp dir to speak of; this is relative to whatever dir was preopened.
+    PathBuf::from(format!("{}.lock", name))
+}
+
+pub fn system_lock_path(name: &str) -> PathBuf {
+    user_lock_path(name)
+}
+
+pub fn process_alive(_pid: u32) -> Result<bool, Error> {
+    Err(Error::Unsupported)
+}
+
+pub fn set_cloexec<F: AsFile>(_f: &F, _cloexec: bool) -> Result<(), Error> {
+    Err(Error::Unsupported)
+}
+
+pub fn as_raw<F: AsFile>(f: &F) -> RawDescriptor {
+    f.as_fd().as_raw_fd()
+}
+
+pub fn into_raw<F: IntoRawFile>(f: F) -> RawDescriptor {
+    f.into_raw_fd()
+}
+
+pub unsafe fn from_raw<F: FromRawFile>(raw: RawDescriptor) -> F {
+    F::from_raw_fd(raw)
+}
+
+pub fn is_held<F: AsFile>(_f: &F, _style: &LockStyle) -> Result<bool, Error> {
+    Err(Error::Unsupported)
+}
+
+pub fn query<F: AsFile>(_f: &F, _style: &LockStyle) -> Result<bool, Error> {
+    Err(Error::Unsupported)
+}
+
+pub fn file_id<F: AsFile>(_f: &F) -> Result<(u64, u64), Error> {
+    Err(Error::Unsupported)
+}
+
+pub fn path_id(_path: &Path) -> Result<Option<(u64, u64)>, Error> {
+    Err(Error::Unsupported)
+}
+
+#[cfg(debug_assertions)]
+pub fn unlinked<F: AsFile>(_f: &F) -> bool {
+    false
+}
+
+// Link counts aren't exposed on stable std here; hard_link's own result is trusted instead.
+pub fn link_count(_path: &Path) -> Option<u64> {
+    None
+}
+
+pub fn filesystem(_path: &Path) -> Result<Filesystem, Error> {
+    Ok(Filesystem::Other)
+}
+
+pub fn check_mandatory<F: AsFile>(_f: &F) -> Result<(), Error> {
+    Err(Error::Unsupported)
+}
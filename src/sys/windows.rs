@@ -0,0 +1,336 @@
+pub use std::os::windows::io::{
+    AsHandle as AsFile, AsRawHandle as AsRawFile, FromRawHandle as FromRawFile,
+    IntoRawHandle as IntoRawFile, RawHandle as RawDescriptor,
+};
+
+use std::fs::{File, OpenOptions};
+use std::io::Error as IOError;
+use std::path::{Path, PathBuf};
+
+use windows_sys::Win32::{
+    Foundation::{
+        CloseHandle, SetHandleInformation, ERROR_ACCESS_DENIED, ERROR_INVALID_HANDLE,
+        ERROR_INVALID_PARAMETER, ERROR_IO_PENDING, ERROR_LOCK_VIOLATION, ERROR_NOT_ENOUGH_MEMORY,
+        ERROR_OPERATION_ABORTED, HANDLE, HANDLE_FLAG_INHERIT, STILL_ACTIVE,
+    },
+    Storage::FileSystem::{
+        GetFileInformationByHandle, LockFileEx, UnlockFileEx, BY_HANDLE_FILE_INFORMATION,
+        FILE_FLAG_BACKUP_SEMANTICS, LOCKFILE_EXCLUSIVE_LOCK, LOCKFILE_FAIL_IMMEDIATELY,
+    },
+    System::Threading::{GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION},
+    System::IO::OVERLAPPED,
+};
+
+use crate::backend::Filesystem;
+use crate::{Error, LockStyle, LockType};
+
+// Like flock(2), LockFileEx locks belong to the handle rather than the process.
+pub const DEFAULT_STYLE: LockStyle = LockStyle::Flock;
+
+impl From<IOError> for Error {
+    fn from(e: IOError) -> Self {
+        match e.raw_os_error().map(|code| code as u32) {
+            Some(ERROR_INVALID_HANDLE) => Error::InvalidFd,
+            Some(ERROR_OPERATION_ABORTED) => Error::Interrupted,
+            Some(ERROR_INVALID_PARAMETER) => Error::InvalidOperation,
+            Some(ERROR_NOT_ENOUGH_MEMORY) => Error::OutOfMemory,
+            Some(ERROR_LOCK_VIOLATION) | Some(ERROR_IO_PENDING) => Error::WouldBlock,
+            _ => Error::Other(e),
+        }
+    }
+}
+
+// The error code each variant is mapped from. The rest are produced by this crate itself.
+pub fn raw_os_error(e: &Error) -> Option<i32> {
+    match e {
+        Error::InvalidFd => Some(ERROR_INVALID_HANDLE as i32),
+        Error::Interrupted => Some(ERROR_OPERATION_ABORTED as i32),
+        Error::InvalidOperation => Some(ERROR_INVALID_PARAMETER as i32),
+        Error::OutOfMemory => Some(ERROR_NOT_ENOUGH_MEMORY as i32),
+        Error::WouldBlock => Some(ERROR_LOCK_VIOLATION as i32),
+        Error::Other(e) => e.raw_os_error(),
+        _ => None,
+    }
+}
+
+fn lock_file<F: AsFile>(
+    f: &F,
+    lock_type: &LockType,
+    start: u64,
+    len: u64,
+    blocking: bool,
+) -> Result<(), Error> {
+    let mut flags = match lock_type {
+        LockType::Exclusive => LOCKFILE_EXCLUSIVE_LOCK,
+        LockType::Shared => 0,
+    };
+    if !blocking {
+        flags |= LOCKFILE_FAIL_IMMEDIATELY;
+    }
+    unsafe {
+        let mut overlapped: OVERLAPPED = std::mem::zeroed();
+        overlapped.Anonymous.Anonymous.Offset = start as u32;
+        overlapped.Anonymous.Anonymous.OffsetHigh = (start >> 32) as u32;
+        if LockFileEx(
+            f.as_handle().as_raw_handle() as HANDLE,
+            flags,
+            0,
+            len as u32,
+            (len >> 32) as u32,
+            &mut overlapped,
+        ) == 0
+        {
+            return Err(IOError::last_os_error().into());
+        }
+    }
+    Ok(())
+}
+
+// UnlockFileEx never waits, so `blocking` has no effect on Windows.
+fn unlock_file<F: AsFile>(f: &F, start: u64, len: u64) -> Result<(), Error> {
+    unsafe {
+        let mut overlapped: OVERLAPPED = std::mem::zeroed();
+        overlapped.Anonymous.Anonymous.Offset = start as u32;
+        overlapped.Anonymous.Anonymous.OffsetHigh = (start >> 32) as u32;
+        if UnlockFileEx(
+            f.as_handle().as_raw_handle() as HANDLE,
+            0,
+            len as u32,
+            (len >> 32) as u32,
+            &mut overlapped,
+        ) == 0
+        {
+            return Err(IOError::last_os_error().into());
+        }
+    }
+    Ok(())
+}
+
+// Locking the maximum range starting at offset 0 covers the whole file, which is the
+// closest equivalent to flock(2).
+pub fn lock<F: AsFile>(
+    f: &F,
+    style: &LockStyle,
+    lock_type: &LockType,
+    blocking: bool,
+) -> Result<(), Error> {
+    if *style != LockStyle::Flock {
+        return Err(Error::Unsupported);
+    }
+    lock_file(f, lock_type, 0, u64::MAX, blocking)
+}
+
+pub fn unlock<F: AsFile>(f: &F, style: &LockStyle, _blocking: bool) -> Result<(), Error> {
+    if *style != LockStyle::Flock {
+        return Err(Error::Unsupported);
+    }
+    unlock_file(f, 0, u64::MAX)
+}
+
+// LockFileEx stacks locks instead of converting them. Taking the shared lock first and then
+// unlocking once drops the exclusive lock underneath; the other direction can't be done.
+pub fn relock<F: AsFile>(
+    f: &F,
+    style: &LockStyle,
+    from: &LockType,
+    to: &LockType,
+    blocking: bool,
+) -> Result<(), Error> {
+    if *style != LockStyle::Flock {
+        return Err(Error::Unsupported);
+    }
+    match (from, to) {
+        (LockType::Exclusive, LockType::Shared) => {
+            lock_file(f, to, 0, u64::MAX, blocking)?;
+            unlock_file(f, 0, u64::MAX)
+        }
+        (LockType::Shared, LockType::Exclusive) => Err(Error::Unsupported),
+        _ => Ok(()),
+    }
+}
+
+// Byte-range locks on Windows always belong to the handle, so both record lock styles map to
+// the same call. A `len` of 0 extends the range past the end of the file, as with fcntl(2).
+pub fn lock_range<F: AsFile>(
+    f: &F,
+    style: &LockStyle,
+    lock_type: &LockType,
+    start: u64,
+    len: u64,
+    blocking: bool,
+) -> Result<(), Error> {
+    if *style == LockStyle::Flock {
+        return Err(Error::InvalidOperation);
+    }
+    lock_file(f, lock_type, start, range_len(start, len), blocking)
+}
+
+pub fn unlock_range<F: AsFile>(
+    f: &F,
+    style: &LockStyle,
+    start: u64,
+    len: u64,
+    _blocking: bool,
+) -> Result<(), Error> {
+    if *style == LockStyle::Flock {
+        return Err(Error::InvalidOperation);
+    }
+    unlock_file(f, start, range_len(start, len))
+}
+
+fn range_len(start: u64, len: u64) -> u64 {
+    if len == 0 {
+        u64::MAX - start
+    } else {
+        len
+    }
+}
+
+pub fn open_locked(
+    path: &Path,
+    style: &LockStyle,
+    lock_type: &LockType,
+    blocking: bool,
+) -> Result<File, Error> {
+    let f = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    lock(&f, style, lock_type, blocking)?;
+    Ok(f)
+}
+
+pub fn io_error(e: IOError) -> Error {
+    e.into()
+}
+
+pub fn hostname() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_else(|_| String::from("localhost"))
+}
+
+pub fn user_lock_path(name: &str) -> PathBuf {
+    lock_path("LOCALAPPDATA", name)
+}
+
+pub fn system_lock_path(name: &str) -> PathBuf {
+    lock_path("ProgramData", name)
+}
+
+fn lock_path(var: &str, name: &str) -> PathBuf {
+    match std::env::var_os(var) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => std::env::temp_dir(),
+    }
+    .join(format!("{}.lock", name))
+}
+
+pub fn process_alive(pid: u32) -> Result<bool, Error> {
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if process == 0 {
+            let e = IOError::last_os_error();
+            return match e.raw_os_error().map(|c| c as u32) {
+                Some(ERROR_ACCESS_DENIED) => Ok(true),
+                Some(ERROR_INVALID_PARAMETER) => Ok(false),
+                _ => Err(e.into()),
+            };
+        }
+        let mut code = 0;
+        let res = GetExitCodeProcess(process, &mut code);
+        let e = IOError::last_os_error();
+        CloseHandle(process);
+        if res == 0 {
+            return Err(e.into());
+        }
+        Ok(code == STILL_ACTIVE as u32)
+    }
+}
+
+pub fn set_cloexec<F: AsFile>(f: &F, cloexec: bool) -> Result<(), Error> {
+    let flags = if cloexec { 0 } else { HANDLE_FLAG_INHERIT };
+    unsafe {
+        if SetHandleInformation(
+            f.as_handle().as_raw_handle() as HANDLE,
+            HANDLE_FLAG_INHERIT,
+            flags,
+        ) == 0
+        {
+            return Err(IOError::last_os_error().into());
+        }
+    }
+    Ok(())
+}
+
+pub fn as_raw<F: AsFile>(f: &F) -> RawDescriptor {
+    f.as_handle().as_raw_handle()
+}
+
+pub fn into_raw<F: IntoRawFile>(f: F) -> RawDescriptor {
+    f.into_raw_handle()
+}
+
+pub unsafe fn from_raw<F: FromRawFile>(raw: RawDescriptor) -> F {
+    F::from_raw_handle(raw)
+}
+
+pub fn is_held<F: AsFile>(_f: &F, _style: &LockStyle) -> Result<bool, Error> {
+    Err(Error::Unsupported)
+}
+
+pub fn query<F: AsFile>(_f: &F, _style: &LockStyle) -> Result<bool, Error> {
+    Err(Error::Unsupported)
+}
+
+// (volume serial number, file index): the Windows equivalent of (st_dev, st_ino).
+pub fn file_id<F: AsFile>(f: &F) -> Result<(u64, u64), Error> {
+    unsafe {
+        let mut info: BY_HANDLE_FILE_INFORMATION = std::mem::zeroed();
+        if GetFileInformationByHandle(f.as_handle().as_raw_handle() as HANDLE, &mut info) == 0 {
+            return Err(IOError::last_os_error().into());
+        }
+        Ok((
+            info.dwVolumeSerialNumber as u64,
+            (info.nFileIndexHigh as u64) << 32 | info.nFileIndexLow as u64,
+        ))
+    }
+}
+
+// LockFileEx locks belong to the handle they were taken through, so opening and closing another
+// one here leaves them alone. It asks for no access, so it works whatever the file's sharing.
+pub fn path_id(path: &Path) -> Result<Option<(u64, u64)>, Error> {
+    use std::os::windows::fs::OpenOptionsExt;
+    match OpenOptions::new()
+        .access_mode(0)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)
+    {
+        Ok(f) => file_id(&f).map(Some),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Link counts aren't exposed on stable std here; hard_link's own result is trusted instead.
+#[cfg(debug_assertions)]
+pub fn unlinked<F: AsFile>(f: &F) -> bool {
+    unsafe {
+        let mut info: BY_HANDLE_FILE_INFORMATION = std::mem::zeroed();
+        GetFileInformationByHandle(f.as_handle().as_raw_handle() as HANDLE, &mut info) != 0
+            && info.nNumberOfLinks == 0
+    }
+}
+
+pub fn link_count(_path: &Path) -> Option<u64> {
+    None
+}
+
+pub fn filesystem(_path: &Path) -> Result<Filesystem, Error> {
+    Ok(Filesystem::Other)
+}
+
+// Byte-range locks on Windows are always mandatory.
+pub fn check_mandatory<F: AsFile>(_f: &F) -> Result<(), Error> {
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use std::io::{IoSlice, Result as IOResult, SeekFrom};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+
+use crate::backend::LockBackend;
+use crate::{AsFile, FdLock, LockStyle};
+
+/// A locked [`tokio::fs::File`]. Lock it with [`FdLock::lock_async`], do I/O on the guard
+/// directly through tokio's async I/O traits, and release it with [`FdLock::unlock_async`].
+/// Flush before releasing: tokio finishes file writes in the background, so unflushed data can
+/// land after the lock is gone.
+pub type TokioFdLock<B = LockStyle> = FdLock<tokio::fs::File, B>;
+
+impl<F, B> AsyncRead for FdLock<F, B>
+where
+    F: AsFile + AsyncRead + Unpin,
+    B: LockBackend + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<IOResult<()>> {
+        Pin::new(&mut **self.get_mut()).poll_read(cx, buf)
+    }
+}
+impl<F, B> AsyncWrite for FdLock<F, B>
+where
+    F: AsFile + AsyncWrite + Unpin,
+    B: LockBackend + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<IOResult<usize>> {
+        Pin::new(&mut **self.get_mut()).poll_write(cx, buf)
+    }
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<IOResult<usize>> {
+        Pin::new(&mut **self.get_mut()).poll_write_vectored(cx, bufs)
+    }
+    fn is_write_vectored(&self) -> bool {
+        (**self).is_write_vectored()
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IOResult<()>> {
+        Pin::new(&mut **self.get_mut()).poll_flush(cx)
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IOResult<()>> {
+        Pin::new(&mut **self.get_mut()).poll_shutdown(cx)
+    }
+}
+impl<F, B> AsyncSeek for FdLock<F, B>
+where
+    F: AsFile + AsyncSeek + Unpin,
+    B: LockBackend + Unpin,
+{
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> IOResult<()> {
+        Pin::new(&mut **self.get_mut()).start_seek(position)
+    }
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IOResult<u64>> {
+        Pin::new(&mut **self.get_mut()).poll_complete(cx)
+    }
+}
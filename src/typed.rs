@@ -0,0 +1,79 @@
+use crate::backend::LockBackend;
+use crate::{AsFile, Error, FdLock, LockStyle, LockType, UnlockError};
+
+/// An exclusive lock, known to be one at compile time. Hands out `&mut F`.
+pub struct ExclusiveGuard<F: AsFile, B: LockBackend = LockStyle>(FdLock<F, B>);
+
+/// A shared lock, known to be one at compile time. Only hands out `&F`, since other processes
+/// may be reading the file at the same time.
+pub struct SharedGuard<F: AsFile, B: LockBackend = LockStyle>(FdLock<F, B>);
+
+macro_rules! guard {
+    ($guard:ident, $lock_type:expr) => {
+        impl<F: AsFile> $guard<F> {
+            /// Locks `f` with the default [`LockStyle`].
+            pub fn lock(f: F, blocking: bool) -> Result<Self, Error> {
+                Self::lock_with(f, LockStyle::default(), blocking)
+            }
+        }
+        impl<F: AsFile, B: LockBackend> $guard<F, B> {
+            pub fn lock_with(f: F, backend: B, blocking: bool) -> Result<Self, Error> {
+                FdLock::lock_with(f, backend, $lock_type, blocking).map($guard)
+            }
+            pub fn backend(&self) -> &B {
+                self.0.backend()
+            }
+            pub fn lock_type(&self) -> LockType {
+                $lock_type
+            }
+            /// Releases the lock and returns the inner handle. On failure the error holds the
+            /// lock as an untyped [`FdLock`], as [`FdLock::unlock`]'s does.
+            pub fn unlock(self) -> Result<F, UnlockError<F, B>> {
+                self.0.unlock()
+            }
+        }
+        impl<F: AsFile, B: LockBackend> std::ops::Deref for $guard<F, B> {
+            type Target = F;
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+    };
+}
+guard!(ExclusiveGuard, LockType::Exclusive);
+guard!(SharedGuard, LockType::Shared);
+
+impl<F: AsFile, B: LockBackend> std::ops::DerefMut for ExclusiveGuard<F, B> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+impl<F: AsFile, B: LockBackend> ExclusiveGuard<F, B> {
+    /// Gives up the compile-time guarantee, returning the untyped guard.
+    pub fn into_inner(self) -> FdLock<F, B> {
+        self.0
+    }
+    /// See [`FdLock::downgrade`].
+    pub fn downgrade(self) -> Result<SharedGuard<F, B>, (Self, Error)> {
+        self.0
+            .downgrade()
+            .map(SharedGuard)
+            .map_err(|(lock, e)| (ExclusiveGuard(lock), e))
+    }
+}
+impl<F: AsFile, B: LockBackend> SharedGuard<F, B> {
+    /// See [`FdLock::upgrade`], including its warning about deadlocks.
+    pub fn upgrade(self) -> Result<ExclusiveGuard<F, B>, (Self, Error)> {
+        self.0
+            .upgrade()
+            .map(ExclusiveGuard)
+            .map_err(|(lock, e)| (SharedGuard(lock), e))
+    }
+    /// See [`FdLock::try_upgrade`].
+    pub fn try_upgrade(self) -> Result<ExclusiveGuard<F, B>, (Self, Error)> {
+        self.0
+            .try_upgrade()
+            .map(ExclusiveGuard)
+            .map_err(|(lock, e)| (SharedGuard(lock), e))
+    }
+}
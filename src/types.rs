@@ -0,0 +1,36 @@
+use crate::sys;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum LockType {
+    Exclusive,
+    Shared,
+}
+
+/// The kernel primitive used to take the lock.
+///
+/// No style's locks belong to a thread, so as a backend it is `Send` and `Sync`, and guards may
+/// be released from any thread.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum LockStyle {
+    /// `flock(2)`: the lock belongs to the open file description, so it is shared by `dup`ed
+    /// descriptors and released when the last of them is closed. `LockFileEx` on Windows.
+    Flock,
+    /// Whole-file `fcntl(2)` record locks: the lock belongs to the process, and is released as
+    /// soon as the process closes *any* descriptor for the file. Works over NFS.
+    Fcntl,
+    /// Linux open file description locks (`F_OFD_SETLK`): whole-file record locks that, like
+    /// `flock(2)`, belong to the open file description, so threads closing unrelated
+    /// descriptors for the same file don't release them.
+    Ofd,
+}
+impl Default for LockStyle {
+    /// `Flock`, unless the platform lacks a native `flock(2)`. Record locks can be asked for
+    /// anywhere else by passing `LockStyle::Fcntl` explicitly.
+    fn default() -> Self {
+        sys::DEFAULT_STYLE
+    }
+}
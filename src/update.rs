@@ -0,0 +1,27 @@
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::lockfile::lock_path;
+use crate::{sys, Error, LockType};
+
+/// Rewrites the file at `path` (created if needed) in place, under an exclusive lock: reads it
+/// whole, passes the contents to `func`, and writes back what it returns, synced before the
+/// lock is released. For small shared files like counters and tokens.
+///
+/// A crash part way through the write can leave the file torn; [`atomic_write`](crate::atomic_write)
+/// avoids that at the cost of replacing the file.
+pub fn update<P: AsRef<Path>, Func: FnOnce(Vec<u8>) -> Vec<u8>>(
+    path: P,
+    func: Func,
+) -> Result<(), Error> {
+    let mut lock = lock_path(path.as_ref(), LockType::Exclusive, true)?;
+    let mut contents = Vec::new();
+    lock.read_to_end(&mut contents).map_err(sys::io_error)?;
+    let contents = func(contents);
+    lock.set_len(0).map_err(sys::io_error)?;
+    lock.seek(SeekFrom::Start(0)).map_err(sys::io_error)?;
+    lock.write_all(&contents).map_err(sys::io_error)?;
+    lock.sync_all().map_err(sys::io_error)?;
+    lock.unlock()?;
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd};
+
+use crate::{Error, FdLock, LockStyle};
+
+/// A [`tokio_uring::fs::File`] that can be locked. tokio-uring files only expose their raw
+/// descriptor; this owns the file, so the descriptor can be safely borrowed from it.
+pub struct UringFile(tokio_uring::fs::File);
+impl UringFile {
+    pub fn into_inner(self) -> tokio_uring::fs::File {
+        self.0
+    }
+}
+impl From<tokio_uring::fs::File> for UringFile {
+    fn from(f: tokio_uring::fs::File) -> Self {
+        UringFile(f)
+    }
+}
+impl std::ops::Deref for UringFile {
+    type Target = tokio_uring::fs::File;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl AsFd for UringFile {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        unsafe { BorrowedFd::borrow_raw(self.0.as_raw_fd()) }
+    }
+}
+
+/// A locked [`UringFile`]. The file isn't `Send`, so lock it with
+/// [`LockFuture`](crate::LockFuture), which keeps the file on the polling thread and only
+/// hands its wake-ups to the shared timer thread, and finish with [`FdLock::close_async`]
+/// rather than [`FdLock::unlock`].
+///
+/// Only `close_async` waits for the ring. Dropping the guard, like `unlock`, releases the lock
+/// right away with an ordinary blocking call on the dropping thread, so operations still in
+/// flight on the file may complete after the lock is gone; and on a network filesystem the
+/// call may stall the runtime while it waits.
+pub type UringFdLock = FdLock<UringFile, LockStyle>;
+
+impl UringFdLock {
+    /// Closes the file through the ring, which releases the lock. tokio-uring only closes a file
+    /// once every operation in flight on it has completed, so even a write whose future was
+    /// dropped can't land after the lock is gone.
+    pub async fn close_async(self) -> Result<(), Error> {
+        let (f, _) = self.into_locked_parts();
+        f.0.close().await.map_err(crate::sys::io_error)
+    }
+}
@@ -0,0 +1,179 @@
+use std::time::{Duration, Instant};
+
+use crate::backend::LockBackend;
+use crate::{AsFile, Error, FdLock, LockStyle, LockType, OnInterrupt, RetryPolicy};
+
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Passed to contention callbacks while waiting for a lock someone else holds.
+#[derive(Clone, Copy, Debug)]
+pub struct ContentionInfo {
+    /// The kind of lock being waited for.
+    pub lock_type: LockType,
+    /// How many attempts have failed so far.
+    pub attempts: u32,
+    /// How long ago the first attempt was made.
+    pub waited: Duration,
+}
+
+/// Picks the real-time signal [`FdLock::lock_timeout_signal`] interrupts its wait with, as an
+/// offset from `SIGRTMIN()`; the default is 8. Fails with [`Error::InvalidOperation`] if that's
+/// past `SIGRTMAX()`, or if a different signal already has this crate's handler.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn set_lock_timeout_signal(offset: i32) -> Result<(), Error> {
+    crate::sys::set_lock_timeout_signal(offset)
+}
+
+impl<F: AsFile> FdLock<F> {
+    /// Like [`FdLock::lock`] with `blocking` set, but calls `on_contention` if the lock is held
+    /// by someone else, and periodically while waiting for it; see
+    /// [`FdLock::lock_retry_reporting`]. Useful for telling users what they're waiting on.
+    pub fn lock_reporting<Func: FnMut(&ContentionInfo)>(
+        f: F,
+        lock_type: LockType,
+        on_contention: Func,
+    ) -> Result<Self, Error> {
+        Self::lock_retry_reporting(
+            f,
+            LockStyle::default(),
+            lock_type,
+            &RetryPolicy::default(),
+            None,
+            on_contention,
+        )
+    }
+    /// Locks `f` with the default [`LockStyle`], waiting at most `timeout` for it before failing
+    /// with [`Error::Timeout`].
+    pub fn lock_timeout(f: F, lock_type: LockType, timeout: Duration) -> Result<Self, Error> {
+        Self::lock_timeout_with(f, LockStyle::default(), lock_type, timeout)
+    }
+    /// Like [`FdLock::lock_timeout`], but on Linux and Android waits in a real blocking call that
+    /// a timer signal interrupts at the timeout, rather than polling, so the lock is picked up
+    /// as soon as it's free. Elsewhere this is [`FdLock::lock_timeout`].
+    ///
+    /// The signal is `SIGRTMIN() + 8`, or whichever [`set_lock_timeout_signal`] picked. The
+    /// first call installs a no-op handler for it for the rest of the process's life, but only
+    /// if nothing else had: should the application have its own handler there (or ignore the
+    /// signal), that's left alone and this polls like [`FdLock::lock_timeout`].
+    ///
+    /// [`set_lock_timeout_signal`]: crate::set_lock_timeout_signal
+    pub fn lock_timeout_signal(
+        f: F,
+        lock_type: LockType,
+        timeout: Duration,
+    ) -> Result<Self, Error> {
+        let style = LockStyle::default();
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        match crate::sys::lock_timeout_signal(&f, &style, &lock_type, timeout) {
+            Err(Error::Unsupported) => Self::lock_timeout_with(f, style, lock_type, timeout),
+            res => res.map(|()| FdLock::assume_locked(f, style, lock_type)),
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        Self::lock_timeout_with(f, style, lock_type, timeout)
+    }
+    /// Like [`FdLock::lock_timeout`], but gives up at `deadline`, so several acquisitions can
+    /// share one overall deadline. A deadline already in the past still gets one attempt.
+    pub fn lock_deadline(f: F, lock_type: LockType, deadline: Instant) -> Result<Self, Error> {
+        Self::lock_deadline_with(f, LockStyle::default(), lock_type, deadline)
+    }
+}
+impl<F: AsFile, B: LockBackend> FdLock<F, B> {
+    /// Like [`FdLock::lock_with`], but handles an interrupted wait as `on_interrupt` says rather
+    /// than always failing with [`Error::Interrupted`].
+    pub fn lock_interruptible(
+        f: F,
+        backend: B,
+        lock_type: LockType,
+        blocking: bool,
+        on_interrupt: OnInterrupt,
+    ) -> Result<Self, Error> {
+        loop {
+            let res = if blocking {
+                backend.acquire(&f, &lock_type)
+            } else {
+                backend.try_acquire(&f, &lock_type)
+            };
+            match res {
+                Ok(()) => return Ok(FdLock::assume_locked(f, backend, lock_type)),
+                Err(Error::Interrupted) if on_interrupt.retry() => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    /// Like [`FdLock::lock_timeout`], with an explicit backend.
+    pub fn lock_timeout_with(
+        f: F,
+        backend: B,
+        lock_type: LockType,
+        timeout: Duration,
+    ) -> Result<Self, Error> {
+        Self::lock_deadline_with(f, backend, lock_type, Instant::now() + timeout)
+    }
+    /// Like [`FdLock::lock_deadline`], with an explicit backend and the default
+    /// [`RetryPolicy`].
+    pub fn lock_deadline_with(
+        f: F,
+        backend: B,
+        lock_type: LockType,
+        deadline: Instant,
+    ) -> Result<Self, Error> {
+        Self::lock_retry(
+            f,
+            backend,
+            lock_type,
+            &RetryPolicy::default(),
+            Some(deadline),
+        )
+    }
+    /// Retries a non-blocking attempt, sleeping between attempts as `policy` says, until the
+    /// lock is taken or `deadline` (if any) passes.
+    pub fn lock_retry(
+        f: F,
+        backend: B,
+        lock_type: LockType,
+        policy: &RetryPolicy,
+        deadline: Option<Instant>,
+    ) -> Result<Self, Error> {
+        Self::lock_retry_reporting(f, backend, lock_type, policy, deadline, |_| ())
+    }
+    /// Like [`FdLock::lock_retry`], but calls `on_contention` when the first attempt finds the
+    /// lock held, and again about once a second for as long as it stays held.
+    pub fn lock_retry_reporting<Func: FnMut(&ContentionInfo)>(
+        f: F,
+        backend: B,
+        lock_type: LockType,
+        policy: &RetryPolicy,
+        deadline: Option<Instant>,
+        mut on_contention: Func,
+    ) -> Result<Self, Error> {
+        let started = Instant::now();
+        let mut reported: Option<Instant> = None;
+        let mut failures = 0;
+        loop {
+            match backend.try_acquire(&f, &lock_type) {
+                Ok(()) => return Ok(FdLock::assume_locked(f, backend, lock_type)),
+                Err(Error::WouldBlock) => (),
+                Err(e) => return Err(e),
+            }
+            failures += 1;
+            let now = Instant::now();
+            if reported.is_none_or(|at| now - at >= REPORT_INTERVAL) {
+                reported = Some(now);
+                on_contention(&ContentionInfo {
+                    lock_type,
+                    attempts: failures,
+                    waited: now - started,
+                });
+            }
+            let mut delay = policy.delay(failures);
+            if let Some(deadline) = deadline {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err(Error::Timeout);
+                }
+                delay = std::cmp::min(delay, deadline - now);
+            }
+            std::thread::sleep(delay);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::fs::File;
+use std::io::ErrorKind as IOErrorKind;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::{sys, Error, LockStyle, LockType};
+
+/// Waits on what other processes do with a file, without locking it.
+///
+/// Both waits poll, backing off up to 50ms between looks. On Linux and Android they also watch
+/// the file's directory with inotify, so a local change, or a holder closing the file, is
+/// picked up immediately.
+#[derive(Debug)]
+pub enum LockWatcher {}
+impl LockWatcher {
+    /// Waits until nobody holds a lock of the default [`LockStyle`] on the file at `path`,
+    /// failing with [`Error::Timeout`] after `timeout`. A missing file has no locks.
+    pub fn wait_released<P: AsRef<Path>>(path: P, timeout: Duration) -> Result<(), Error> {
+        Self::wait_released_with(path, LockStyle::default(), timeout)
+    }
+    /// Like [`LockWatcher::wait_released`] for locks of `style`.
+    ///
+    /// Locks are asked about, never taken: record locks with `F_GETLK` (`F_OFD_GETLK` on
+    /// Linux), and flock(2) locks through `/proc/locks` on Linux and Android. Only where
+    /// neither works, as for flock(2) elsewhere and on Windows, does each look take and drop an
+    /// exclusive lock with a non-blocking try, which someone else's try could lose to. With
+    /// [`LockStyle::Fcntl`], remember that closing any descriptor for a file drops this
+    /// process's record locks on it, the watcher's included.
+    pub fn wait_released_with<P: AsRef<Path>>(
+        path: P,
+        style: LockStyle,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        let path = path.as_ref();
+        let deadline = Instant::now() + timeout;
+        // Before opening the file, so that the watcher's own descriptor is never seen closing.
+        let waker = Waker::new(path, true);
+        let f = match File::open(path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == IOErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(sys::io_error(e)),
+        };
+        poll(&waker, deadline, |_| Ok(!held(&f, &style)?))
+    }
+    /// Waits until the file at `path` changes: it's written, replaced, created or removed.
+    /// Fails with [`Error::Timeout`] after `timeout`.
+    ///
+    /// Without inotify, a change is only seen once the file's identity, length or modification
+    /// time differ, so a same-length write within the filesystem's timestamp granularity can be
+    /// missed.
+    pub fn wait_modified<P: AsRef<Path>>(path: P, timeout: Duration) -> Result<(), Error> {
+        let path = path.as_ref();
+        let deadline = Instant::now() + timeout;
+        let waker = Waker::new(path, false);
+        let before = fingerprint(path)?;
+        poll(&waker, deadline, |changed| {
+            Ok(changed || fingerprint(path)? != before)
+        })
+    }
+}
+
+// Calls `done` until it returns true, passing whether the watch (if any) saw a change since
+// the last call.
+fn poll<Func: FnMut(bool) -> Result<bool, Error>>(
+    waker: &Waker,
+    deadline: Instant,
+    mut done: Func,
+) -> Result<(), Error> {
+    let mut changed = false;
+    let mut wait = Duration::from_millis(1);
+    loop {
+        if done(changed)? {
+            return Ok(());
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(Error::Timeout);
+        }
+        changed = waker.wait(wait.min(deadline - now))?;
+        wait = (wait * 2).min(Duration::from_millis(50));
+    }
+}
+
+fn held(f: &File, style: &LockStyle) -> Result<bool, Error> {
+    match sys::query(f, style) {
+        Err(Error::Unsupported) => match sys::lock(f, style, &LockType::Exclusive, false) {
+            Ok(()) => sys::unlock(f, style, false).map(|()| false),
+            Err(Error::WouldBlock) => Ok(true),
+            Err(e) => Err(e),
+        },
+        res => res,
+    }
+}
+
+type Fingerprint = Option<((u64, u64), u64, Option<SystemTime>)>;
+
+fn fingerprint(path: &Path) -> Result<Fingerprint, Error> {
+    let f = match File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == IOErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(sys::io_error(e)),
+    };
+    let metadata = f.metadata().map_err(sys::io_error)?;
+    Ok(Some((
+        sys::file_id(&f)?,
+        metadata.len(),
+        metadata.modified().ok(),
+    )))
+}
+
+// Sleeps between looks, or waits on an inotify watch where there is one. A watch that can't
+// be set up (say the per-user limit is reached) just leaves polling.
+struct Waker {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    watch: Option<sys::Watch>,
+}
+impl Waker {
+    #[allow(unused_variables)]
+    fn new(path: &Path, closes: bool) -> Self {
+        Waker {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            watch: sys::watch(path, closes).ok(),
+        }
+    }
+    fn wait(&self, timeout: Duration) -> Result<bool, Error> {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if let Some(watch) = &self.watch {
+            return watch.wait(timeout);
+        }
+        std::thread::sleep(timeout);
+        Ok(false)
+    }
+}
@@ -0,0 +1,87 @@
+use std::fmt::Arguments;
+use std::fs::OpenOptions;
+use std::io::{IoSlice, Result as IOResult, Write};
+use std::path::Path;
+
+use crate::backend::LockBackend;
+use crate::{sys, AsFile, Error, FdLock, LockStyle, LockType};
+
+/// Appends `bytes` to the file at `path` (created if needed) as one record: opens it for
+/// appending, waits for an exclusive lock, writes, syncs and releases. See [`LockedWriter`] for
+/// appending many records through one handle.
+pub fn locked_append<P: AsRef<Path>>(path: P, bytes: &[u8]) -> Result<(), Error> {
+    let f = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .map_err(sys::io_error)?;
+    let mut lock = FdLock::lock(f, LockType::Exclusive, true)?;
+    lock.write_all(bytes).map_err(sys::io_error)?;
+    lock.sync_data().map_err(sys::io_error)?;
+    lock.unlock()?;
+    Ok(())
+}
+
+/// A writer that holds an exclusive lock on its file only for the length of each call, so
+/// several processes appending to one log can share it without interleaving their records.
+///
+/// Each `write`, `write_all`, `write_fmt` and `flush` takes the lock on its own, and
+/// [`record`](LockedWriter::record) holds it across several writes. A single `write` may be
+/// short, so records should go through `write_all` or `write_fmt` (`writeln!`), or a
+/// [`LineWriter`](std::io::LineWriter) wrapped around this, not into a buffer beneath it. The
+/// file should be opened for appending.
+#[derive(Debug)]
+pub struct LockedWriter<W: AsFile + Write, B: LockBackend = LockStyle> {
+    inner: W,
+    backend: B,
+}
+impl<W: AsFile + Write> LockedWriter<W> {
+    /// Uses the default [`LockStyle`].
+    pub fn new(inner: W) -> Self {
+        Self::with_backend(inner, LockStyle::default())
+    }
+}
+impl<W: AsFile + Write, B: LockBackend> LockedWriter<W, B> {
+    pub fn with_backend(inner: W, backend: B) -> Self {
+        LockedWriter { inner, backend }
+    }
+    /// Runs `func` with the lock held, for records written in several pieces.
+    pub fn record<T, Func: FnOnce(&mut W) -> IOResult<T>>(&mut self, func: Func) -> IOResult<T> {
+        self.backend.acquire(&self.inner, &LockType::Exclusive)?;
+        let res = func(&mut self.inner);
+        let released = self.backend.release(&self.inner);
+        let res = res?;
+        released?;
+        Ok(res)
+    }
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+    /// Writes made through the returned reference aren't locked.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+impl<W: AsFile + Write, B: LockBackend> Write for LockedWriter<W, B> {
+    fn write(&mut self, buf: &[u8]) -> IOResult<usize> {
+        self.record(|w| w.write(buf))
+    }
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> IOResult<usize> {
+        self.record(|w| w.write_vectored(bufs))
+    }
+    fn write_all(&mut self, buf: &[u8]) -> IOResult<()> {
+        self.record(|w| w.write_all(buf))
+    }
+    fn write_fmt(&mut self, args: Arguments<'_>) -> IOResult<()> {
+        self.record(|w| w.write_fmt(args))
+    }
+    fn flush(&mut self) -> IOResult<()> {
+        self.record(|w| w.flush())
+    }
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "tokio")]
+
+use std::fs::File;
+use std::future::Future;
+use std::path::PathBuf;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+use fd_lock_rs::{FdLock, LockType};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("fd-lock-rs-{}-{}", name, std::process::id()))
+}
+
+// The blocking call is left waiting in the kernel when its future is dropped; once the holder
+// lets go it gets the lock, and has to give it straight back.
+#[test]
+fn dropped_lock_future_does_not_keep_the_lock() {
+    let path = temp_path("async-cancel");
+    File::create(&path).unwrap();
+    let held = FdLock::lock(
+        File::options().read(true).write(true).open(&path).unwrap(),
+        LockType::Exclusive,
+        true,
+    )
+    .unwrap();
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        let mut pending = Box::pin(FdLock::lock_async(
+            File::options().read(true).write(true).open(&path).unwrap(),
+            LockType::Exclusive,
+        ));
+        std::future::poll_fn(|cx| {
+            assert!(pending.as_mut().poll(cx).is_pending());
+            Poll::Ready(())
+        })
+        .await;
+        std::thread::sleep(Duration::from_millis(50));
+    });
+    drop(held);
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        if FdLock::try_lock(
+            File::options().read(true).write(true).open(&path).unwrap(),
+            LockType::Exclusive,
+        )
+        .unwrap()
+        .is_some()
+        {
+            break;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "the cancelled lock was never released"
+        );
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    drop(rt);
+    let _ = std::fs::remove_file(&path);
+}
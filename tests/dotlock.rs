@@ -0,0 +1,87 @@
+use std::fs::File;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use fd_lock_rs::backend::DotLock;
+use fd_lock_rs::{Error, FdLock, LockType};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("fd-lock-rs-{}-{}", name, std::process::id()))
+}
+
+fn age(path: &PathBuf, by: Duration) {
+    let f = File::options().write(true).open(path).unwrap();
+    f.set_modified(SystemTime::now() - by).unwrap();
+}
+
+#[test]
+fn stale_lock_is_broken_and_refreshed_one_is_not() {
+    let path = temp_path("dotlock-stale");
+    let guarded = temp_path("dotlock-stale-file");
+    File::create(&guarded).unwrap();
+    let backend = DotLock::new(&path).stale_after(Duration::from_secs(60));
+
+    std::fs::write(&path, "1\n").unwrap();
+    age(&path, Duration::from_secs(3600));
+    let lock = FdLock::try_lock_with(
+        File::open(&guarded).unwrap(),
+        backend.clone(),
+        LockType::Exclusive,
+    )
+    .unwrap()
+    .expect("stale lock wasn't broken");
+
+    age(&path, Duration::from_secs(3600));
+    lock.backend().refresh().unwrap();
+    let contender = FdLock::try_lock_with(
+        File::open(&guarded).unwrap(),
+        backend.clone(),
+        LockType::Exclusive,
+    )
+    .unwrap();
+    assert!(contender.is_none());
+
+    drop(lock);
+    assert!(!path.exists());
+    let _ = std::fs::remove_file(&guarded);
+}
+
+#[test]
+fn refresh_refuses_someone_elses_lock() {
+    let path = temp_path("dotlock-foreign");
+    std::fs::write(&path, "1\n").unwrap();
+    assert!(matches!(
+        DotLock::new(&path).refresh(),
+        Err(Error::InvalidOperation)
+    ));
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn releasing_a_broken_lock_leaves_the_new_holders() {
+    let path = temp_path("dotlock-rebroken");
+    let guarded = temp_path("dotlock-rebroken-file");
+    File::create(&guarded).unwrap();
+    let backend = DotLock::new(&path).stale_after(Duration::from_secs(60));
+    let lock = |backend: &DotLock| {
+        FdLock::try_lock_with(
+            File::open(&guarded).unwrap(),
+            backend.clone(),
+            LockType::Exclusive,
+        )
+        .unwrap()
+        .unwrap()
+    };
+
+    let first = lock(&backend);
+    age(&path, Duration::from_secs(3600));
+    let second = lock(&backend);
+    let err = first.unlock().unwrap_err();
+    assert!(matches!(err.error(), Error::InvalidOperation));
+    assert!(path.exists());
+    std::mem::forget(err);
+
+    second.unlock().unwrap();
+    assert!(!path.exists());
+    let _ = std::fs::remove_file(&guarded);
+}
@@ -0,0 +1,36 @@
+#![cfg(unix)]
+
+use std::fs::File;
+use std::path::PathBuf;
+
+use fd_lock_rs::{Error, FdLock, LockStyle, LockType};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("fd-lock-rs-{}-{}", name, std::process::id()))
+}
+
+// flock(2) would release the exclusive lock before taking the shared one.
+#[test]
+fn flock_locks_are_not_downgraded() {
+    let path = temp_path("downgrade-flock");
+    File::create(&path).unwrap();
+    let lock = FdLock::lock_with(
+        File::open(&path).unwrap(),
+        LockStyle::Flock,
+        LockType::Exclusive,
+        true,
+    )
+    .unwrap();
+    let (lock, e) = lock.downgrade().unwrap_err();
+    assert!(matches!(e, Error::Unsupported));
+    assert_eq!(lock.lock_type(), LockType::Exclusive);
+    let other = FdLock::lock_with(
+        File::open(&path).unwrap(),
+        LockStyle::Flock,
+        LockType::Shared,
+        false,
+    );
+    assert!(matches!(other, Err(Error::WouldBlock)));
+    drop(lock);
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,12 @@
+use std::io::{Error as IOError, ErrorKind as IOErrorKind};
+
+use fd_lock_rs::Error;
+
+#[test]
+fn other_errors_without_an_errno_compare_by_kind() {
+    let other = || Error::Other(IOError::new(IOErrorKind::InvalidData, "bad lock file"));
+    assert_eq!(other(), other());
+    assert_eq!(other(), other().clone());
+    assert_ne!(other(), Error::Other(IOError::from(IOErrorKind::NotFound)));
+    assert_ne!(other(), Error::Other(IOError::from_raw_os_error(22)));
+}
@@ -0,0 +1,30 @@
+use std::fs::File;
+use std::path::PathBuf;
+
+use fd_lock_rs::{FlockExt, LockType};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("fd-lock-rs-{}-{}", name, std::process::id()))
+}
+
+// Through method syntax on a `File`, where std's own locking methods would win a name clash.
+#[test]
+fn method_syntax_gives_guards() {
+    let path = temp_path("ext");
+    File::create(&path).unwrap();
+    let file = File::open(&path).unwrap();
+    let other = File::open(&path).unwrap();
+
+    let shared = file.flock_shared().unwrap();
+    assert_eq!(shared.lock_type(), LockType::Shared);
+    assert!(other.try_flock_shared().unwrap().is_some());
+    assert!(other.try_flock_exclusive().unwrap().is_none());
+    drop(shared);
+
+    let exclusive = file.flock_exclusive().unwrap();
+    assert_eq!(exclusive.lock_type(), LockType::Exclusive);
+    assert!(other.try_flock_shared().unwrap().is_none());
+    drop(exclusive);
+    assert!(other.try_flock_exclusive().unwrap().is_some());
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+
+use fd_lock_rs::{HolderStatus, Lockfile};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("fd-lock-rs-{}-{}", name, std::process::id()))
+}
+
+#[test]
+fn force_break_rewrites_stale_pid() {
+    let path = temp_path("force-break");
+    let mut child = std::process::Command::new(std::env::current_exe().unwrap())
+        .arg("--list")
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let dead = child.id();
+    child.wait().unwrap();
+    std::fs::write(&path, format!("{}\n", dead)).unwrap();
+    assert_eq!(
+        Lockfile::holder_alive(&path).unwrap(),
+        HolderStatus::Dead(dead)
+    );
+
+    let lock = Lockfile::force_break(&path).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        format!("{}\n", std::process::id())
+    );
+    assert_eq!(
+        Lockfile::holder_alive(&path).unwrap(),
+        HolderStatus::Alive(std::process::id())
+    );
+    lock.remove().unwrap();
+    let _ = std::fs::remove_file(format!("{}.break", path.display()));
+}
+
+#[test]
+fn pids_that_name_process_groups_are_not_pids() {
+    let path = temp_path("group-pid");
+    for contents in ["0\n", "4294967295\n", "2147483648\n"] {
+        std::fs::write(&path, contents).unwrap();
+        assert_eq!(Lockfile::holder_alive(&path).unwrap(), HolderStatus::NoPid);
+    }
+    let _ = std::fs::remove_file(&path);
+}
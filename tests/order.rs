@@ -0,0 +1,64 @@
+#![cfg(debug_assertions)]
+
+use std::fs::File;
+use std::path::PathBuf;
+
+use fd_lock_rs::{set_lock_order_check, FdLock, LockOrderCheck, LockType};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("fd-lock-rs-{}-{}", name, std::process::id()))
+}
+
+fn lock(path: &PathBuf) -> FdLock<File> {
+    FdLock::lock(File::create(path).unwrap(), LockType::Exclusive, true).unwrap()
+}
+
+// One test, since the setting is process-wide.
+#[test]
+fn inversions() {
+    let (a, b) = (temp_path("order-a"), temp_path("order-b"));
+
+    // Off by default: nothing is recorded, so nothing is found later either.
+    {
+        let _a = lock(&a);
+        let _b = lock(&b);
+    }
+    set_lock_order_check(LockOrderCheck::Panic);
+    {
+        let _b = lock(&b);
+        let _a = lock(&a);
+    }
+
+    // Now b-then-a is on record.
+    let inverted = std::thread::spawn(move || {
+        let _a = lock(&a);
+        let _b = lock(&b);
+    })
+    .join();
+    assert!(inverted.is_err());
+
+    // A guard released on another thread no longer counts as held by the one that took it.
+    let (c, d) = (temp_path("order-c"), temp_path("order-d"));
+    let (tx, rx) = std::sync::mpsc::channel();
+    let (done_tx, done_rx) = std::sync::mpsc::channel();
+    let locker = {
+        let (c, d) = (c.clone(), d.clone());
+        std::thread::spawn(move || {
+            tx.send(lock(&c)).unwrap();
+            done_rx.recv().unwrap();
+            let _d = lock(&d);
+        })
+    };
+    drop(rx.recv().unwrap());
+    done_tx.send(()).unwrap();
+    locker.join().unwrap();
+    {
+        let _d = lock(&d);
+        let _c = lock(&c);
+    }
+
+    set_lock_order_check(LockOrderCheck::Off);
+    for path in ["order-a", "order-b", "order-c", "order-d"] {
+        let _ = std::fs::remove_file(temp_path(path));
+    }
+}
@@ -0,0 +1,43 @@
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use fd_lock_rs::{Error, FdLock, LockType, Reentrancy, ReentrantFileLock};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("fd-lock-rs-{}-{}", name, std::process::id()))
+}
+
+#[test]
+fn try_lock_does_not_wait_behind_a_blocked_lock() {
+    let path = temp_path("reentrant-try");
+    File::create(&path).unwrap();
+    let held = FdLock::lock(
+        File::options().read(true).write(true).open(&path).unwrap(),
+        LockType::Exclusive,
+        true,
+    )
+    .unwrap();
+    let lock = Arc::new(ReentrantFileLock::new(
+        File::options().read(true).write(true).open(&path).unwrap(),
+        LockType::Exclusive,
+        Reentrancy::Process,
+    ));
+    let waiter = {
+        let lock = lock.clone();
+        std::thread::spawn(move || drop(lock.lock().unwrap()))
+    };
+    std::thread::sleep(Duration::from_millis(50));
+
+    let start = Instant::now();
+    assert!(matches!(lock.try_lock(), Err(Error::WouldBlock)));
+    assert_eq!(lock.count(), 0);
+    assert!(start.elapsed() < Duration::from_millis(50));
+
+    drop(held);
+    waiter.join().unwrap();
+    assert_eq!(lock.count(), 0);
+    drop(lock.try_lock().unwrap());
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,44 @@
+use std::fs::File;
+use std::path::PathBuf;
+
+use fd_lock_rs::FileRwLock;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("fd-lock-rs-{}-{}", name, std::process::id()))
+}
+
+fn open(path: &PathBuf) -> File {
+    File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .unwrap()
+}
+
+#[test]
+fn readers_share_one_lock() {
+    let path = temp_path("rwlock-readers");
+    let lock = FileRwLock::new(open(&path));
+    let other = FileRwLock::new(open(&path));
+
+    let first = lock.read().unwrap();
+    let second = lock
+        .try_read()
+        .unwrap()
+        .expect("readers should not exclude each other");
+    assert!(lock.try_write().unwrap().is_none());
+    assert!(other.try_write().unwrap().is_none());
+
+    // The file stays locked until the last reader is gone.
+    drop(first);
+    assert!(other.try_write().unwrap().is_none());
+    second.unlock().unwrap();
+    other
+        .try_write()
+        .unwrap()
+        .expect("the last reader should release the file");
+
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,77 @@
+use std::fs::File;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use fd_lock_rs::backend::LockBackend;
+use fd_lock_rs::{AsFile, Error, FdLock, LockService, LockType};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("fd-lock-rs-{}-{}", name, std::process::id()))
+}
+
+struct Panics;
+impl LockBackend for Panics {
+    fn acquire<F: AsFile>(&self, _f: &F, _lock_type: &LockType) -> Result<(), Error> {
+        panic!("backend failure")
+    }
+    fn try_acquire<F: AsFile>(&self, f: &F, lock_type: &LockType) -> Result<(), Error> {
+        self.acquire(f, lock_type)
+    }
+    fn release<F: AsFile>(&self, _f: &F) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[test]
+fn panicking_backend_fails_only_its_request() {
+    let path = temp_path("service-panic");
+    File::create(&path).unwrap();
+    let service = LockService::new(1).unwrap();
+    let failed = service.lock_with(
+        File::options().read(true).write(true).open(&path).unwrap(),
+        Panics,
+        LockType::Exclusive,
+    );
+    assert!(matches!(failed.wait(), Err(Error::Interrupted)));
+    let lock = service
+        .lock(
+            File::options().read(true).write(true).open(&path).unwrap(),
+            LockType::Exclusive,
+        )
+        .wait()
+        .unwrap();
+    drop(lock);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn free_locks_do_not_wait_behind_held_ones() {
+    let held_path = temp_path("service-held");
+    let free_path = temp_path("service-free");
+    File::create(&held_path).unwrap();
+    File::create(&free_path).unwrap();
+    let open = |path: &PathBuf| File::options().read(true).write(true).open(path).unwrap();
+    let held = FdLock::lock(open(&held_path), LockType::Exclusive, true).unwrap();
+    let service = LockService::new(1).unwrap();
+    let waiting: Vec<_> = (0..3)
+        .map(|_| service.lock(open(&held_path), LockType::Exclusive))
+        .collect();
+
+    let free = service.lock(open(&free_path), LockType::Exclusive);
+    let (send, recv) = std::sync::mpsc::channel();
+    std::thread::spawn(move || send.send(free.wait().map(drop)).unwrap());
+    recv.recv_timeout(Duration::from_secs(5))
+        .expect("the free lock waited behind held ones")
+        .unwrap();
+
+    // Cancelled requests are dropped on their next turn, and the worker is still free.
+    drop(waiting);
+    drop(held);
+    let lock = service
+        .lock(open(&held_path), LockType::Exclusive)
+        .wait()
+        .unwrap();
+    drop(lock);
+    let _ = std::fs::remove_file(&held_path);
+    let _ = std::fs::remove_file(&free_path);
+}
@@ -0,0 +1,45 @@
+#![cfg(any(target_os = "linux", target_os = "android"))]
+
+use std::fs::File;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use fd_lock_rs::{Error, FdLock, LockType};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("fd-lock-rs-{}-{}", name, std::process::id()))
+}
+
+// Each thread's timer keeps firing after its own wait ends, so this dies of the signal if any
+// thread's cleanup puts the default disposition back while another's timer is still armed.
+#[test]
+fn concurrent_timed_locks() {
+    let path = temp_path("timeout-signal");
+    File::create(&path).unwrap();
+    let held = FdLock::lock(
+        File::options().read(true).write(true).open(&path).unwrap(),
+        LockType::Exclusive,
+        true,
+    )
+    .unwrap();
+    let threads: Vec<_> = (0..8)
+        .map(|i| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                for _ in 0..20 {
+                    let f = File::options().read(true).write(true).open(&path).unwrap();
+                    let timeout = Duration::from_millis(1 + i % 3);
+                    match FdLock::lock_timeout_signal(f, LockType::Shared, timeout) {
+                        Err(Error::Timeout) => {}
+                        res => panic!("expected a timeout, got {:?}", res.map(|_| ())),
+                    }
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    drop(held);
+    let _ = std::fs::remove_file(&path);
+}
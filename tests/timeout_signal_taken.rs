@@ -0,0 +1,73 @@
+#![cfg(any(target_os = "linux", target_os = "android"))]
+
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use fd_lock_rs::{set_lock_timeout_signal, Error, FdLock, LockType};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("fd-lock-rs-{}-{}", name, std::process::id()))
+}
+
+static CAUGHT: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn handler(_: libc::c_int) {
+    CAUGHT.fetch_add(1, Ordering::Relaxed);
+}
+
+fn disposition(signal: libc::c_int) -> libc::sighandler_t {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        assert_eq!(libc::sigaction(signal, std::ptr::null(), &mut action), 0);
+        action.sa_sigaction
+    }
+}
+
+// Polls instead, and the application's handler is still there afterwards.
+#[test]
+fn an_existing_handler_is_left_alone() {
+    let offset = 3;
+    let signal = libc::SIGRTMIN() + offset;
+    let ours = handler as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = ours;
+        assert_eq!(libc::sigaction(signal, &action, std::ptr::null_mut()), 0);
+    }
+    set_lock_timeout_signal(offset).unwrap();
+
+    let path = temp_path("timeout-signal-taken");
+    let open = || {
+        File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .unwrap()
+    };
+    let held = FdLock::lock(open(), LockType::Exclusive, true).unwrap();
+    assert!(matches!(
+        FdLock::lock_timeout_signal(open(), LockType::Exclusive, Duration::from_millis(20)),
+        Err(Error::Timeout)
+    ));
+    assert_eq!(disposition(signal), ours);
+    assert_eq!(CAUGHT.load(Ordering::Relaxed), 0);
+
+    drop(held);
+    drop(
+        FdLock::lock_timeout_signal(open(), LockType::Exclusive, Duration::from_millis(20))
+            .unwrap(),
+    );
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn offsets_past_sigrtmax_are_refused() {
+    assert!(matches!(
+        set_lock_timeout_signal(libc::SIGRTMAX() - libc::SIGRTMIN() + 1),
+        Err(Error::InvalidOperation)
+    ));
+}
@@ -0,0 +1,48 @@
+use std::fs::File;
+use std::path::PathBuf;
+
+use fd_lock_rs::{FdLock, LockStyle, LockType};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("fd-lock-rs-{}-{}", name, std::process::id()))
+}
+
+// The duplicate would share the process's record lock rather than the open file description.
+#[cfg(unix)]
+#[test]
+fn fcntl_locks_are_not_cloned() {
+    let path = temp_path("try-clone-fcntl");
+    let f = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .unwrap();
+    let lock = FdLock::lock_with(f, LockStyle::Fcntl, LockType::Exclusive, true).unwrap();
+    assert!(matches!(
+        lock.try_clone(),
+        Err(fd_lock_rs::Error::Unsupported)
+    ));
+    drop(lock);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn flock_clones_share_the_lock() {
+    let path = temp_path("try-clone-flock");
+    File::create(&path).unwrap();
+    let lock = FdLock::lock_with(
+        File::open(&path).unwrap(),
+        LockStyle::Flock,
+        LockType::Shared,
+        true,
+    )
+    .unwrap();
+    let clone = lock.try_clone().unwrap();
+    drop(lock);
+    let exclusive = FdLock::try_lock(File::open(&path).unwrap(), LockType::Exclusive).unwrap();
+    assert!(exclusive.is_some());
+    drop(clone);
+    let _ = std::fs::remove_file(&path);
+}
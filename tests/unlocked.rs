@@ -0,0 +1,43 @@
+use std::fs::File;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use fd_lock_rs::backend::LockBackend;
+use fd_lock_rs::{AsFile, Error, FdLock, LockType};
+
+// Locks once, then refuses; counts releases.
+#[derive(Clone, Default)]
+struct Once {
+    acquired: Arc<AtomicUsize>,
+    released: Arc<AtomicUsize>,
+}
+impl LockBackend for Once {
+    fn acquire<F: AsFile>(&self, _: &F, _: &LockType) -> Result<(), Error> {
+        match self.acquired.fetch_add(1, Ordering::SeqCst) {
+            0 => Ok(()),
+            _ => Err(Error::WouldBlock),
+        }
+    }
+    fn try_acquire<F: AsFile>(&self, f: &F, lock_type: &LockType) -> Result<(), Error> {
+        self.acquire(f, lock_type)
+    }
+    fn release<F: AsFile>(&self, _: &F) -> Result<(), Error> {
+        self.released.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[test]
+fn failed_reacquire_releases_nothing_more() {
+    let backend = Once::default();
+    let f = File::open(std::env::current_exe().unwrap()).unwrap();
+    let mut lock = FdLock::lock_with(f, backend.clone(), LockType::Exclusive, true).unwrap();
+    assert!(matches!(lock.unlocked(|_| ()), Err(Error::WouldBlock)));
+    assert_eq!(backend.released.load(Ordering::SeqCst), 1);
+    assert!(matches!(
+        lock.unlocked(|_| ()),
+        Err(Error::InvalidOperation)
+    ));
+    drop(lock);
+    assert_eq!(backend.released.load(Ordering::SeqCst), 1);
+}
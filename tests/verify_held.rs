@@ -0,0 +1,70 @@
+#![cfg(any(target_os = "linux", target_os = "android"))]
+
+use std::fs::File;
+use std::path::PathBuf;
+
+use fd_lock_rs::{FdLock, LockStyle, LockType};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("fd-lock-rs-{}-{}", name, std::process::id()))
+}
+
+#[test]
+fn fcntl_lock_shared_with_another_owner() {
+    let path = temp_path("verify-fcntl");
+    File::create(&path).unwrap();
+    // An OFD lock is a different owner, and the first one F_GETLK would report.
+    let other = FdLock::lock_with(
+        File::open(&path).unwrap(),
+        LockStyle::Ofd,
+        LockType::Shared,
+        true,
+    )
+    .unwrap();
+    let lock = FdLock::lock_with(
+        File::open(&path).unwrap(),
+        LockStyle::Fcntl,
+        LockType::Shared,
+        true,
+    )
+    .unwrap();
+    assert!(lock.verify_still_held().unwrap());
+    // Closing any descriptor for the file drops the process's fcntl(2) locks on it.
+    drop(File::open(&path).unwrap());
+    assert!(!lock.verify_still_held().unwrap());
+    drop(other);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn flock_is_attributed_to_the_guard() {
+    let path = temp_path("verify-flock");
+    File::create(&path).unwrap();
+    let lock = FdLock::lock_with(
+        File::open(&path).unwrap(),
+        LockStyle::Flock,
+        LockType::Shared,
+        true,
+    )
+    .unwrap();
+    let other = FdLock::lock_with(
+        File::open(&path).unwrap(),
+        LockStyle::Flock,
+        LockType::Shared,
+        true,
+    )
+    .unwrap();
+    assert!(lock.verify_still_held().unwrap());
+    // Claims a lock its description never took; the ones on the file belong to others.
+    let impostor = unsafe {
+        FdLock::adopt_with(
+            File::open(&path).unwrap(),
+            LockStyle::Flock,
+            LockType::Shared,
+        )
+    };
+    assert!(!impostor.verify_still_held().unwrap());
+    drop(impostor);
+    drop(other);
+    let _ = std::fs::remove_file(&path);
+}
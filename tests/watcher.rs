@@ -0,0 +1,54 @@
+use std::fs::File;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use fd_lock_rs::{Error, FdLock, LockStyle, LockType, LockWatcher};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("fd-lock-rs-{}-{}", name, std::process::id()))
+}
+
+fn waits_for_release(name: &str, style: LockStyle) {
+    let path = temp_path(name);
+    File::create(&path).unwrap();
+    let held =
+        FdLock::lock_with(File::open(&path).unwrap(), style, LockType::Shared, true).unwrap();
+    assert!(matches!(
+        LockWatcher::wait_released_with(&path, style, Duration::from_millis(50)),
+        Err(Error::Timeout)
+    ));
+    let releaser = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        drop(held);
+    });
+    LockWatcher::wait_released_with(&path, style, Duration::from_secs(10)).unwrap();
+    releaser.join().unwrap();
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn wait_released_flock() {
+    waits_for_release("watch-flock", LockStyle::Flock);
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn wait_released_ofd() {
+    waits_for_release("watch-ofd", LockStyle::Ofd);
+}
+
+#[test]
+fn wait_modified_sees_a_write() {
+    let path = temp_path("watch-modified");
+    std::fs::write(&path, "before").unwrap();
+    let writer = {
+        let path = path.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            std::fs::write(&path, "after!").unwrap();
+        })
+    };
+    LockWatcher::wait_modified(&path, Duration::from_secs(10)).unwrap();
+    writer.join().unwrap();
+    let _ = std::fs::remove_file(&path);
+}